@@ -0,0 +1,218 @@
+//! Comparison and hashing of LEB128 numbers by value, as described in the
+//! crate docs.
+
+use core::cmp::Ordering;
+use core::hash::{Hash, Hasher};
+
+use {EncodeILeb128, EncodeULeb128, ILeb128, ULeb128};
+#[cfg(feature = "alloc")]
+use {ILeb128Owned, ULeb128Owned};
+
+// The 7-bit groups of a canonical number, most significant first.
+fn groups<'a>(bytes: &'a [u8]) -> impl Iterator<Item = u8> + 'a {
+    bytes.iter().rev().map(|byte| byte & 0b0111_1111)
+}
+
+fn cmp_unsigned(a: &[u8], b: &[u8]) -> Ordering {
+    let a = &a[..ULeb128(a).canonical_len()];
+    let b = &b[..ULeb128(b).canonical_len()];
+    // A longer canonical number has a non-zero high group, so it is larger.
+    a.len().cmp(&b.len()).then_with(|| groups(a).cmp(groups(b)))
+}
+
+fn cmp_signed(a: &[u8], b: &[u8]) -> Ordering {
+    let a = &a[..ILeb128(a).canonical_len()];
+    let b = &b[..ILeb128(b).canonical_len()];
+    let a_negative = a[a.len() - 1] & 0b0100_0000 != 0;
+    let b_negative = b[b.len() - 1] & 0b0100_0000 != 0;
+    match (a_negative, b_negative) {
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        _ => {
+            // A longer canonical number has a larger magnitude. Numbers of the
+            // same length and sign order like their two's complement bits.
+            let by_len = a.len().cmp(&b.len());
+            let by_len = if a_negative { by_len.reverse() } else { by_len };
+            by_len.then_with(|| groups(a).cmp(groups(b)))
+        }
+    }
+}
+
+fn hash_unsigned<H: Hasher>(bytes: &[u8], state: &mut H) {
+    let bytes = &bytes[..ULeb128(bytes).canonical_len()];
+    state.write_usize(bytes.len());
+    for group in groups(bytes) {
+        state.write_u8(group);
+    }
+}
+
+fn hash_signed<H: Hasher>(bytes: &[u8], state: &mut H) {
+    let bytes = &bytes[..ILeb128(bytes).canonical_len()];
+    state.write_usize(bytes.len());
+    for group in groups(bytes) {
+        state.write_u8(group);
+    }
+}
+
+macro_rules! impl_cmp {
+    ($t: ty, $cmp: ident, $hash: ident) => {
+        impl<'a> PartialEq for $t {
+            fn eq(&self, other: &$t) -> bool {
+                $cmp(&self.0, &other.0) == Ordering::Equal
+            }
+        }
+
+        impl<'a> Eq for $t {}
+
+        impl<'a> PartialOrd for $t {
+            fn partial_cmp(&self, other: &$t) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl<'a> Ord for $t {
+            fn cmp(&self, other: &$t) -> Ordering {
+                $cmp(&self.0, &other.0)
+            }
+        }
+
+        impl<'a> Hash for $t {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                $hash(&self.0, state)
+            }
+        }
+    }
+}
+
+impl_cmp!(ULeb128<'a>, cmp_unsigned, hash_unsigned);
+impl_cmp!(ILeb128<'a>, cmp_signed, hash_signed);
+#[cfg(feature = "alloc")]
+impl_cmp!(ULeb128Owned, cmp_unsigned, hash_unsigned);
+#[cfg(feature = "alloc")]
+impl_cmp!(ILeb128Owned, cmp_signed, hash_signed);
+
+// Comparisons between owned and borrowed numbers.
+#[cfg(feature = "alloc")]
+macro_rules! impl_cmp_owned {
+    ($owned_t: ty, $t: ty, $cmp: ident) => {
+        impl<'a> PartialEq<$t> for $owned_t {
+            fn eq(&self, other: &$t) -> bool {
+                $cmp(&self.0, other.0) == Ordering::Equal
+            }
+        }
+
+        impl<'a> PartialEq<$owned_t> for $t {
+            fn eq(&self, other: &$owned_t) -> bool {
+                $cmp(self.0, &other.0) == Ordering::Equal
+            }
+        }
+
+        impl<'a> PartialOrd<$t> for $owned_t {
+            fn partial_cmp(&self, other: &$t) -> Option<Ordering> {
+                Some($cmp(&self.0, other.0))
+            }
+        }
+
+        impl<'a> PartialOrd<$owned_t> for $t {
+            fn partial_cmp(&self, other: &$owned_t) -> Option<Ordering> {
+                Some($cmp(self.0, &other.0))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl_cmp_owned!(ULeb128Owned, ULeb128<'a>, cmp_unsigned);
+#[cfg(feature = "alloc")]
+impl_cmp_owned!(ILeb128Owned, ILeb128<'a>, cmp_signed);
+
+// Comparisons with native integers, by encoding the integer on the stack.
+macro_rules! impl_cmp_int {
+    ($t: ty, $cmp: ident, $encode: ident, $($int: ty),*) => {
+        $(
+            impl<'a> PartialEq<$int> for $t {
+                fn eq(&self, other: &$int) -> bool {
+                    self.partial_cmp(other) == Some(Ordering::Equal)
+                }
+            }
+
+            impl<'a> PartialOrd<$int> for $t {
+                fn partial_cmp(&self, other: &$int) -> Option<Ordering> {
+                    let (bytes, count) = other.$encode();
+                    Some($cmp(&self.0, &bytes[..count]))
+                }
+            }
+        )*
+    }
+}
+
+impl_cmp_int!(ULeb128<'a>, cmp_unsigned, encode_uleb128_array, u8, u16, u32, u64, u128, usize);
+impl_cmp_int!(ILeb128<'a>, cmp_signed, encode_ileb128_array, i8, i16, i32, i64, i128, isize);
+#[cfg(feature = "alloc")]
+impl_cmp_int!(ULeb128Owned, cmp_unsigned, encode_uleb128_array, u8, u16, u32, u64, u128, usize);
+#[cfg(feature = "alloc")]
+impl_cmp_int!(ILeb128Owned, cmp_signed, encode_ileb128_array, i8, i16, i32, i64, i128, isize);
+
+#[cfg(test)]
+mod test {
+    use core::cmp::Ordering;
+    use core::hash::{Hash, Hasher};
+    use std::collections::hash_map::DefaultHasher;
+    use {ILeb128, ULeb128};
+    #[cfg(feature = "alloc")]
+    use {ILeb128Owned, ULeb128Owned};
+
+    fn hash<T: Hash>(value: T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_eq_by_value() {
+        assert!(ULeb128(&[0x80, 0]) == ULeb128(&[0]));
+        assert!(ULeb128(&[0xE5, 0x8E, 0xA6, 0x80, 0]) == ULeb128(&[0xE5, 0x8E, 0x26]));
+        assert!(ULeb128(&[0x80, 1]) != ULeb128(&[0]));
+        assert!(ILeb128(&[0xff, 0x7f]) == ILeb128(&[0x7f]));
+        assert!(ILeb128(&[0xc0, 0x80, 0]) == ILeb128(&[0xc0, 0]));
+        assert!(ILeb128(&[0x7f]) != ILeb128(&[0xff, 0]));
+
+        assert!(hash(ULeb128(&[0x80, 0x80, 0])) == hash(ULeb128(&[0])));
+        assert!(hash(ILeb128(&[0xff, 0xff, 0x7f])) == hash(ILeb128(&[0x7f])));
+    }
+
+    #[test]
+    fn test_ord() {
+        let mut values = [ULeb128(&[0x80, 1]), ULeb128(&[0xff, 0x80, 0]), ULeb128(&[0x80, 0]), ULeb128(&[0x81, 1])];
+        values.sort();
+        assert!(values[0] == 0u8 && values[1] == 127u8 && values[2] == 128u8 && values[3] == 129u8);
+        assert!(ULeb128(&[0xE5, 0x8E, 0x26]).cmp(&ULeb128(&[0xE5, 0x8E, 0x25])) == Ordering::Greater);
+
+        let mut values = [ILeb128(&[0x40]), ILeb128(&[0xff, 0x7e]), ILeb128(&[0xc0, 0]), ILeb128(&[0x80, 0x7f]), ILeb128(&[0])];
+        values.sort();
+        assert!(values[0] == -129i16 && values[1] == -128i16 && values[2] == -64i16);
+        assert!(values[3] == 0i16 && values[4] == 64i16);
+    }
+
+    #[test]
+    fn test_cmp_int() {
+        assert!(ULeb128(&[0xE5, 0x8E, 0x26]) == 624485u32);
+        assert!(ULeb128(&[0xE5, 0x8E, 0x26]) < 624486u64);
+        assert!(ULeb128(&[0xE5, 0x8E, 0x26]) > 255u8);
+        assert!(ULeb128(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0]) == 0u8);
+        assert!(ILeb128(&[0xff, 0x7e]) == -129i64);
+        assert!(ILeb128(&[0xff, 0x7e]) < -128i8);
+        assert!(ILeb128(&[0xff, 0x7e]) > i128::MIN);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_cmp_owned() {
+        assert!(ULeb128Owned::from_bytes(&[0x80, 0]) == ULeb128(&[0]));
+        assert!(ULeb128(&[0]) == ULeb128Owned::from_bytes(&[0x80, 0]));
+        assert!(ULeb128Owned::from_bytes(&[0x80, 1]) > ULeb128(&[0x7f]));
+        assert!(ILeb128(&[0x7f]) < ILeb128Owned::from_bytes(&[0]));
+        assert!(ILeb128Owned::from_bytes(&[0xff, 0x7f]) == -1i32);
+        assert!(hash(ULeb128Owned::from_bytes(&[0x80, 0])) == hash(ULeb128(&[0])));
+    }
+}
@@ -0,0 +1,169 @@
+//! Encoding and decoding LEB128 numbers in const contexts, e.g., for opcodes
+//! and static tables. See also the `uleb128!` and `sleb128!` macros.
+
+use decode::Partial;
+use Leb128Error;
+
+/// Encode `value` as unsigned LEB128 using exactly `N` bytes, padding with
+/// continuation bytes if necessary. Panics (at compile time, in a const
+/// context) if `value` does not fit in `N` bytes.
+pub const fn uleb128_array<const N: usize>(value: u128) -> [u8; N] {
+    let mut bytes = [0; N];
+    let mut value = value;
+    let mut i = 0;
+    while i < N {
+        bytes[i] = value as u8 & 0b0111_1111;
+        value >>= 7;
+        if i + 1 < N {
+            bytes[i] |= 0b1000_0000;
+        }
+        i += 1;
+    }
+    if N == 0 || value != 0 {
+        panic!("value does not fit in the LEB128 array");
+    }
+    bytes
+}
+
+/// Encode `value` as signed LEB128 using exactly `N` bytes, padding with
+/// continuation bytes if necessary. Panics (at compile time, in a const
+/// context) if `value` does not fit in `N` bytes.
+pub const fn sleb128_array<const N: usize>(value: i128) -> [u8; N] {
+    let mut bytes = [0; N];
+    let mut value = value;
+    let mut i = 0;
+    while i < N {
+        bytes[i] = value as u8 & 0b0111_1111;
+        value >>= 7;
+        if i + 1 < N {
+            bytes[i] |= 0b1000_0000;
+        }
+        i += 1;
+    }
+    // The remaining bits and the sign bit of the last byte must agree.
+    let negative = N > 0 && bytes[N - 1] & 0b0100_0000 != 0;
+    if N == 0 || value != if negative { -1 } else { 0 } {
+        panic!("value does not fit in the LEB128 array");
+    }
+    bytes
+}
+
+/// Decode the unsigned LEB128 number at the start of `bytes`, returning its
+/// value and the number of bytes it uses.
+pub const fn decode_uleb128_const(bytes: &[u8]) -> Result<(u128, usize), Leb128Error> {
+    let mut partial = Partial::new::<u128>(false);
+    match decode_const(&mut partial, bytes) {
+        Ok(result) => Ok((result, partial.count())),
+        Err(e) => Err(e),
+    }
+}
+
+/// Decode the signed LEB128 number at the start of `bytes`, returning its
+/// value and the number of bytes it uses.
+pub const fn decode_sleb128_const(bytes: &[u8]) -> Result<(i128, usize), Leb128Error> {
+    let mut partial = Partial::new::<i128>(true);
+    match decode_const(&mut partial, bytes) {
+        Ok(result) => Ok((result as i128, partial.count())),
+        Err(e) => Err(e),
+    }
+}
+
+const fn decode_const(partial: &mut Partial, bytes: &[u8]) -> Result<u128, Leb128Error> {
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Err(e) = partial.push(bytes[i]) {
+            return Err(e);
+        }
+        if bytes[i] & 0b1000_0000 == 0 {
+            return partial.finish();
+        }
+        i += 1;
+    }
+    Err(Leb128Error::Truncated { offset: bytes.len() })
+}
+
+/// Encode an integer constant as an unsigned LEB128 byte array at compile
+/// time, e.g., `uleb128!(624485) == [0xE5, 0x8E, 0x26]`. Negative constants
+/// fail to compile:
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate leb128;
+/// # fn main() {
+/// let bytes = uleb128!(-1i32);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! uleb128 {
+    ($value: expr) => {{
+        #[allow(unused_comparisons, clippy::absurd_extreme_comparisons)]
+        const _: () = assert!($value >= 0, "uleb128! of a negative value");
+        const BYTES: [u8; $crate::uleb128_len($value as u128)] = $crate::uleb128_array($value as u128);
+        BYTES
+    }}
+}
+
+/// Encode an integer constant as a signed LEB128 byte array at compile time,
+/// e.g., `sleb128!(-129) == [0xff, 0x7e]`. Constants which do not fit in an
+/// `i128` fail to compile:
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate leb128;
+/// # fn main() {
+/// let bytes = sleb128!(u128::MAX);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! sleb128 {
+    ($value: expr) => {{
+        #[allow(unused_comparisons, clippy::absurd_extreme_comparisons)]
+        const _: () = assert!((($value as i128) < 0) == ($value < 0), "sleb128! of a value which does not fit in an i128");
+        const BYTES: [u8; $crate::sleb128_len($value as i128)] = $crate::sleb128_array($value as i128);
+        BYTES
+    }}
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use {EncodeILeb128, EncodeULeb128};
+
+    const OPCODE: [u8; 3] = uleb128!(624485);
+    static TABLE: &[u8] = &sleb128!(-129);
+
+    #[test]
+    fn test_macros() {
+        assert!(OPCODE == [0xE5, 0x8E, 0x26]);
+        assert!(TABLE == [0xff, 0x7e]);
+        assert!(uleb128!(0) == [0]);
+        assert!(uleb128!(u128::MAX)[..] == u128::MAX.encode_uleb128_array().0[..]);
+        assert!(sleb128!(i64::MIN)[..] == i64::MIN.encode_ileb128_array().0[..]);
+        assert!(sleb128!(63) == [0x3f]);
+        assert!(sleb128!(64) == [0xc0, 0]);
+        let (max, count) = (u64::MAX as i128).encode_ileb128_array();
+        assert!(sleb128!(u64::MAX)[..] == max[..count]);
+    }
+
+    #[test]
+    fn test_padded_arrays() {
+        assert!(uleb128_array::<3>(1) == [0x81, 0x80, 0]);
+        assert!(sleb128_array::<2>(-1) == [0xff, 0x7f]);
+        assert!(sleb128_array::<2>(-128) == [0x80, 0x7f]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_array_too_short() {
+        sleb128_array::<1>(64);
+    }
+
+    #[test]
+    fn test_decode_const() {
+        const DECODED: Result<(u128, usize), Leb128Error> = decode_uleb128_const(&[0xE5, 0x8E, 0x26, 42]);
+        assert!(DECODED == Ok((624485, 3)));
+        assert!(decode_sleb128_const(&[0xff, 0x7e]) == Ok((-129, 2)));
+        assert!(decode_sleb128_const(&[0x80, 0x80]) == Err(Leb128Error::Truncated { offset: 2 }));
+        let mut max = [0xff; 19];
+        max[18] = 0x04;
+        assert!(decode_uleb128_const(&max) == Err(Leb128Error::Overflow { target_bits: 128, offset: 18 }));
+    }
+}
@@ -0,0 +1,181 @@
+//! A cursor for parsing buffers which contain LEB128 numbers and other data.
+
+use core::mem;
+
+use {FromLeb128, ILeb128, Leb128Error, ULeb128, ZigZag};
+
+macro_rules! read_leb128 {
+    ($name: ident, $read: ident, $t: ty) => {
+        pub fn $name(&mut self) -> Result<$t, Leb128Error> {
+            self.$read()
+        }
+    }
+}
+
+macro_rules! read_le {
+    ($name: ident, $t: ty) => {
+        /// Read a little-endian fixed-width integer.
+        pub fn $name(&mut self) -> Result<$t, Leb128Error> {
+            let mut bytes = [0; mem::size_of::<$t>()];
+            bytes.copy_from_slice(self.read_bytes(mem::size_of::<$t>())?);
+            Ok(<$t>::from_le_bytes(bytes))
+        }
+    }
+}
+
+/// A position in a byte buffer, from which LEB128 numbers, raw bytes and
+/// fixed-width integers can be read.
+///
+/// Errors are reported with offsets from the start of the buffer (not the
+/// current position), and leave the position unchanged.
+#[derive(Debug, Clone)]
+pub struct Leb128Cursor<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Leb128Cursor<'a> {
+    pub fn new(bytes: &'a [u8]) -> Leb128Cursor<'a> {
+        Leb128Cursor { bytes, position: 0 }
+    }
+
+    /// The offset of the next byte from the start of the buffer.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The bytes which have not yet been read.
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.bytes[self.position..]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+
+    /// Move to `position`, which may be anywhere up to the end of the buffer.
+    pub fn seek(&mut self, position: usize) -> Result<(), Leb128Error> {
+        if position > self.bytes.len() {
+            return Err(Leb128Error::Truncated { offset: self.bytes.len() });
+        }
+        self.position = position;
+        Ok(())
+    }
+
+    /// Move forward by `count` bytes.
+    pub fn skip(&mut self, count: usize) -> Result<(), Leb128Error> {
+        self.read_bytes(count).map(|_| ())
+    }
+
+    /// Read the next `count` bytes.
+    pub fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], Leb128Error> {
+        if count > self.bytes.len() - self.position {
+            return Err(Leb128Error::Truncated { offset: self.bytes.len() });
+        }
+        let result = &self.remaining()[..count];
+        self.position += count;
+        Ok(result)
+    }
+
+    /// Read bytes prefixed by their length as unsigned LEB128.
+    pub fn read_blob(&mut self) -> Result<&'a [u8], Leb128Error> {
+        let start = self.position;
+        let len = self.read_uleb128::<usize>()?;
+        self.read_bytes(len).inspect_err(|_| self.position = start)
+    }
+
+    /// Read an unsigned LEB128 number, e.g., `cursor.read_uleb128::<u32>()`.
+    pub fn read_uleb128<T: FromLeb128>(&mut self) -> Result<T, Leb128Error> {
+        let (number, _) = ULeb128::split_first(self.remaining()).map_err(|e| e.offset_by(self.position))?;
+        self.advance(number.byte_count(), number.try_decode())
+    }
+
+    /// Read a signed LEB128 number, e.g., `cursor.read_sleb128::<i64>()`.
+    pub fn read_sleb128<T: FromLeb128>(&mut self) -> Result<T, Leb128Error> {
+        let (number, _) = ILeb128::split_first(self.remaining()).map_err(|e| e.offset_by(self.position))?;
+        self.advance(number.byte_count(), number.try_decode())
+    }
+
+    /// Read a ZigZag-mapped unsigned LEB128 number, e.g.,
+    /// `cursor.read_zigzag::<i32>()`.
+    pub fn read_zigzag<T: ZigZag>(&mut self) -> Result<T, Leb128Error> {
+        self.read_uleb128::<T::Unsigned>().map(T::from_zigzag)
+    }
+
+    fn advance<T>(&mut self, count: usize, result: Result<T, Leb128Error>) -> Result<T, Leb128Error> {
+        let value = result.map_err(|e| e.offset_by(self.position))?;
+        self.position += count;
+        Ok(value)
+    }
+
+    read_leb128!(read_uleb128_u8, read_uleb128, u8);
+    read_leb128!(read_uleb128_u16, read_uleb128, u16);
+    read_leb128!(read_uleb128_u32, read_uleb128, u32);
+    read_leb128!(read_uleb128_u64, read_uleb128, u64);
+    read_leb128!(read_uleb128_u128, read_uleb128, u128);
+    read_leb128!(read_uleb128_usize, read_uleb128, usize);
+
+    read_leb128!(read_sleb128_i8, read_sleb128, i8);
+    read_leb128!(read_sleb128_i16, read_sleb128, i16);
+    read_leb128!(read_sleb128_i32, read_sleb128, i32);
+    read_leb128!(read_sleb128_i64, read_sleb128, i64);
+    read_leb128!(read_sleb128_i128, read_sleb128, i128);
+    read_leb128!(read_sleb128_isize, read_sleb128, isize);
+
+    read_le!(read_u8, u8);
+    read_le!(read_u16_le, u16);
+    read_le!(read_u32_le, u32);
+    read_le!(read_u64_le, u64);
+    read_le!(read_u128_le, u128);
+    read_le!(read_i8, i8);
+    read_le!(read_i16_le, i16);
+    read_le!(read_i32_le, i32);
+    read_le!(read_i64_le, i64);
+    read_le!(read_i128_le, i128);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cursor() {
+        let bytes = [0xE5, 0x8E, 0x26, 0xff, 0x7e, 0x34, 0x12, 3, b'a', b'b', b'c', 0xff];
+        let mut cursor = Leb128Cursor::new(&bytes);
+        assert!(cursor.read_uleb128_u32() == Ok(624485));
+        assert!(cursor.read_sleb128::<i64>() == Ok(-129));
+        assert!(cursor.read_u16_le() == Ok(0x1234));
+        assert!(cursor.position() == 7);
+        assert!(cursor.read_blob() == Ok(&b"abc"[..]));
+        assert!(cursor.read_i8() == Ok(-1));
+        assert!(cursor.is_empty());
+
+        cursor.seek(3).unwrap();
+        assert!(cursor.remaining()[..2] == [0xff, 0x7e]);
+        cursor.skip(5).unwrap();
+        assert!(cursor.read_bytes(2) == Ok(&b"ab"[..]));
+
+        let mut cursor = Leb128Cursor::new(&[0x80, 0x01, 0x03]);
+        assert!(cursor.read_zigzag::<i8>() == Ok(64));
+        assert!(cursor.read_zigzag::<i64>() == Ok(-2));
+    }
+
+    #[test]
+    fn test_cursor_errors() {
+        let bytes = [0, 0, 0, 0x80, 2, 0x80, 0x80];
+        let mut cursor = Leb128Cursor::new(&bytes);
+        cursor.skip(3).unwrap();
+        assert!(cursor.read_uleb128_u8() == Err(Leb128Error::Overflow { target_bits: 8, offset: 4 }));
+        assert!(cursor.position() == 3);
+        assert!(cursor.read_u64_le() == Err(Leb128Error::Truncated { offset: 7 }));
+        assert!(cursor.read_uleb128_u16() == Ok(256));
+        assert!(cursor.read_sleb128_i32() == Err(Leb128Error::Truncated { offset: 7 }));
+        assert!(cursor.position() == 5);
+        assert!(cursor.seek(8) == Err(Leb128Error::Truncated { offset: 7 }));
+
+        // A blob which is longer than the buffer.
+        let mut cursor = Leb128Cursor::new(&[5, 1, 2]);
+        assert!(cursor.read_blob() == Err(Leb128Error::Truncated { offset: 3 }));
+        assert!(cursor.position() == 0);
+    }
+}
@@ -0,0 +1,430 @@
+//! Decoding LEB128 numbers to integer types.
+
+use core::mem;
+
+use {ILeb128, Leb128Error, ULeb128};
+
+/// Integer types which LEB128 numbers can be decoded to, see
+/// `ULeb128::try_decode` and `ILeb128::try_decode`.
+///
+/// Decoding is by value, so an unsigned number can be decoded to a signed type
+/// if it is small enough, and a signed number can be decoded to an unsigned
+/// type if it is not negative.
+///
+/// The trait is sealed: it is implemented for the primitive integer types and
+/// cannot be implemented outside this crate.
+pub trait FromLeb128: Sized + sealed::Sealed {
+    /// The width of the type in bits.
+    const BITS: u32;
+
+    /// Whether the type is signed.
+    const SIGNED: bool;
+
+    // Truncate a two's complement value to this type.
+    #[doc(hidden)]
+    fn from_bits(bits: u128) -> Self;
+
+    /// Decode an unsigned LEB128 number.
+    fn try_from_uleb128(number: ULeb128) -> Result<Self, Leb128Error> {
+        decode::<Self>(number.0, false).map(Self::from_bits)
+    }
+
+    /// Decode a signed LEB128 number.
+    fn try_from_ileb128(number: ILeb128) -> Result<Self, Leb128Error> {
+        decode::<Self>(number.0, true).map(Self::from_bits)
+    }
+}
+
+/// Read an unsigned LEB128 number from the start of `input` and advance
+/// `input` past it, e.g., `read_uleb128::<u32>(&mut input)`. `input` is not
+/// changed on error.
+pub fn read_uleb128<T: FromLeb128>(input: &mut &[u8]) -> Result<T, Leb128Error> {
+    let (number, rest) = ULeb128::split_first(input)?;
+    let value = number.try_decode()?;
+    *input = rest;
+    Ok(value)
+}
+
+/// Read a signed LEB128 number from the start of `input` and advance `input`
+/// past it, e.g., `read_sleb128::<i64>(&mut input)`. `input` is not changed on
+/// error.
+pub fn read_sleb128<T: FromLeb128>(input: &mut &[u8]) -> Result<T, Leb128Error> {
+    let (number, rest) = ILeb128::split_first(input)?;
+    let value = number.try_decode()?;
+    *input = rest;
+    Ok(value)
+}
+
+/// How to decode numbers which do not fit in the target type.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum OverflowPolicy {
+    /// Return a `Leb128Error::Overflow` error.
+    Checked,
+    /// Clamp to the nearest value of the target type.
+    Saturating,
+    /// Keep the low bits of the two's complement value, like decoders which
+    /// shift without checking.
+    Wrapping,
+}
+
+/// Options for decoding LEB128 numbers, see `ULeb128::try_decode_with` and
+/// `ILeb128::try_decode_with`. The default options decode like `try_decode`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct DecodeOptions {
+    overflow: OverflowPolicy,
+    pub(crate) max_bytes: Option<usize>,
+    pub(crate) max_count: Option<usize>,
+    pub(crate) max_total_bytes: Option<usize>,
+}
+
+impl DecodeOptions {
+    pub fn new() -> DecodeOptions {
+        DecodeOptions {
+            overflow: OverflowPolicy::Checked,
+            max_bytes: None,
+            max_count: None,
+            max_total_bytes: None,
+        }
+    }
+
+    /// Set how numbers which do not fit in the target type are decoded.
+    pub fn overflow(mut self, policy: OverflowPolicy) -> DecodeOptions {
+        self.overflow = policy;
+        self
+    }
+
+    /// Set the maximum length of a single number, reported as
+    /// `Leb128Error::TooLong`. When decoding to an integer type, the default
+    /// is the length of the longest encoding of the type (e.g., 10 bytes for
+    /// `u64`); otherwise, the default is no limit.
+    pub fn max_bytes(mut self, max_bytes: usize) -> DecodeOptions {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Set the maximum number of numbers to read from a buffer, reported as
+    /// `Leb128Error::TooMany`. The default is no limit.
+    pub fn max_count(mut self, max_count: usize) -> DecodeOptions {
+        self.max_count = Some(max_count);
+        self
+    }
+
+    /// Set the maximum number of bytes to read from a buffer, reported as
+    /// `Leb128Error::OverBudget`. The default is no limit.
+    pub fn max_total_bytes(mut self, max_total_bytes: usize) -> DecodeOptions {
+        self.max_total_bytes = Some(max_total_bytes);
+        self
+    }
+}
+
+impl Default for DecodeOptions {
+    fn default() -> DecodeOptions {
+        DecodeOptions::new()
+    }
+}
+
+// Decode the bytes of a single LEB128 number (`bytes` must end with the
+// number's last byte) to the two's complement bits of a `T`.
+fn decode<T: FromLeb128>(bytes: &[u8], signed: bool) -> Result<u128, Leb128Error> {
+    let mut partial = Partial::new::<T>(signed);
+    for &byte in bytes {
+        partial.push(byte)?;
+    }
+    partial.finish()
+}
+
+// As `decode`, using `options`. Also returns whether the number did not fit.
+pub(crate) fn decode_with<T: FromLeb128>(bytes: &[u8], signed: bool, options: DecodeOptions) -> Result<(T, bool), Leb128Error> {
+    let mut partial = Partial::new::<T>(signed).max_bytes(options.max_bytes);
+    for &byte in bytes {
+        partial.push(byte)?;
+    }
+    partial.finish_with(options.overflow).map(|(bits, truncated)| (T::from_bits(bits), truncated))
+}
+
+// Decode the bytes of a single LEB128 number to the two's complement bits of
+// an integer of `bits` bits, which is signed if the number is.
+pub(crate) fn decode_bits(bytes: &[u8], signed: bool, bits: u32) -> Result<u128, Leb128Error> {
+    let mut partial = Partial::with_bits(signed, bits, signed);
+    for &byte in bytes {
+        partial.push(byte)?;
+    }
+    partial.finish()
+}
+
+// The state of a partly decoded LEB128 number, which is checked a byte at a
+// time so that numbers can be decoded from fragmented input.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Partial {
+    result: u128,
+    count: usize,
+    last: u8,
+    // The offsets of the first groups which have bits above `value_bits`
+    // which are not all zero, and not all one.
+    not_zero: Option<usize>,
+    not_one: Option<usize>,
+
+    // Whether the number is signed LEB128.
+    signed: bool,
+    // The width of the target type, whether it is signed, and the number of
+    // bits of its value (i.e., excluding any sign bit).
+    target_bits: u32,
+    target_signed: bool,
+    value_bits: u32,
+    max_bytes: usize,
+}
+
+impl Partial {
+    pub(crate) const fn new<T: FromLeb128>(signed: bool) -> Partial {
+        Partial::with_bits(signed, T::BITS, T::SIGNED)
+    }
+
+    // Decode to an integer of `target_bits` bits (at most 128).
+    pub(crate) const fn with_bits(signed: bool, target_bits: u32, target_signed: bool) -> Partial {
+        Partial {
+            result: 0,
+            count: 0,
+            last: 0,
+            not_zero: None,
+            not_one: None,
+            signed,
+            target_bits,
+            target_signed,
+            value_bits: if target_signed { target_bits - 1 } else { target_bits },
+            // A non-negative signed number needs an extra bit for its sign.
+            max_bytes: if signed && !target_signed { max_bytes(target_bits + 1) } else { max_bytes(target_bits) },
+        }
+    }
+
+    // Override the maximum length of the number.
+    pub(crate) const fn max_bytes(mut self, max_bytes: Option<usize>) -> Partial {
+        if let Some(max_bytes) = max_bytes {
+            self.max_bytes = max_bytes;
+        }
+        self
+    }
+
+    // Start a new number.
+    pub(crate) const fn reset(&mut self) {
+        self.result = 0;
+        self.count = 0;
+        self.last = 0;
+        self.not_zero = None;
+        self.not_one = None;
+    }
+
+    // The number of bytes pushed so far.
+    pub(crate) const fn count(&self) -> usize {
+        self.count
+    }
+
+    // Add the next byte of the number. The caller is responsible for stopping
+    // after a byte without the continuation bit.
+    pub(crate) const fn push(&mut self, byte: u8) -> Result<(), Leb128Error> {
+        if self.count >= self.max_bytes {
+            return Err(Leb128Error::TooLong { max_bytes: self.max_bytes, offset: self.count });
+        }
+
+        let low = byte & 0b0111_1111;
+        let shift = self.count.saturating_mul(7);
+        // Bits of the group above `free` are outside the value.
+        let free = (self.value_bits as usize).saturating_sub(shift);
+        if free < 7 {
+            let mask = 0b0111_1111 >> free << free;
+            if low & mask != 0 && self.not_zero.is_none() {
+                self.not_zero = Some(self.count);
+            }
+            if low & mask != mask && self.not_one.is_none() {
+                self.not_one = Some(self.count);
+            }
+        }
+        if shift < 128 {
+            self.result |= (low as u128) << shift;
+        }
+        self.last = byte;
+        self.count += 1;
+        Ok(())
+    }
+
+    // Check the number fits in the target type, after its last byte has been
+    // pushed, and return its two's complement bits.
+    pub(crate) const fn finish(&self) -> Result<u128, Leb128Error> {
+        if let Some(offset) = self.overflow_offset() {
+            return Err(self.overflow(offset));
+        }
+        Ok(self.bits())
+    }
+
+    // As `finish`, but numbers which do not fit are handled by `policy`.
+    // Also returns whether the number did not fit.
+    pub(crate) const fn finish_with(&self, policy: OverflowPolicy) -> Result<(u128, bool), Leb128Error> {
+        let offset = match self.overflow_offset() {
+            Some(offset) => offset,
+            None => return Ok((self.bits(), false)),
+        };
+        match policy {
+            OverflowPolicy::Checked => Err(self.overflow(offset)),
+            OverflowPolicy::Wrapping => Ok((self.bits(), true)),
+            OverflowPolicy::Saturating => {
+                let value_bits = self.value_bits;
+                if self.negative() {
+                    let min = if self.target_signed { !0 << value_bits } else { 0 };
+                    Ok((min, true))
+                } else {
+                    Ok((!0 >> (128 - value_bits), true))
+                }
+            }
+        }
+    }
+
+    const fn negative(&self) -> bool {
+        self.signed && self.last & 0b0100_0000 != 0
+    }
+
+    // The offset of the first byte which does not fit in the target type.
+    const fn overflow_offset(&self) -> Option<usize> {
+        let negative = self.negative();
+        if negative && !self.target_signed {
+            return Some(self.count - 1);
+        }
+        if negative { self.not_one } else { self.not_zero }
+    }
+
+    // The two's complement bits of the number, i.e., sign extended if
+    // necessary.
+    const fn bits(&self) -> u128 {
+        let shift = self.count.saturating_mul(7);
+        if self.negative() && shift < 128 {
+            return self.result | !0 << shift;
+        }
+        self.result
+    }
+
+    const fn overflow(&self, offset: usize) -> Leb128Error {
+        Leb128Error::Overflow { target_bits: self.target_bits, offset }
+    }
+}
+
+// The length of the longest encoding of a value with `bits` bits.
+const fn max_bytes(bits: u32) -> usize {
+    (bits as usize).div_ceil(7)
+}
+
+pub(crate) mod sealed {
+    // Keeps `FromLeb128` and the encoding traits to the primitive integer
+    // types, whose widths, lengths and `MAX_BYTES` the crate relies on.
+    pub trait Sealed {}
+}
+
+macro_rules! impl_from_leb128 {
+    ($t: ty, $signed: expr) => {
+        impl sealed::Sealed for $t {}
+
+        impl FromLeb128 for $t {
+            const BITS: u32 = mem::size_of::<$t>() as u32 * 8;
+            const SIGNED: bool = $signed;
+
+            fn from_bits(bits: u128) -> $t {
+                bits as $t
+            }
+        }
+    }
+}
+
+impl_from_leb128!(u8, false);
+impl_from_leb128!(u16, false);
+impl_from_leb128!(u32, false);
+impl_from_leb128!(u64, false);
+impl_from_leb128!(u128, false);
+impl_from_leb128!(usize, false);
+impl_from_leb128!(i8, true);
+impl_from_leb128!(i16, true);
+impl_from_leb128!(i32, true);
+impl_from_leb128!(i64, true);
+impl_from_leb128!(i128, true);
+impl_from_leb128!(isize, true);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_generic_decode() {
+        fn decode_all<T: ::FromLeb128>(bytes: &[u8]) -> Result<std::vec::Vec<T>, Leb128Error> {
+            ULeb128::iter(bytes).map(|n| n.and_then(|n| n.try_decode::<T>())).collect()
+        }
+        assert!(decode_all::<u16>(&[42, 0xff, 0xff, 3]) == Ok(vec![42, 0xffff]));
+        assert!(decode_all::<u8>(&[42, 0xff, 0xff, 3]) ==
+                Err(Leb128Error::TooLong { max_bytes: 2, offset: 2 }));
+
+        assert!(ULeb128::from_bytes(&[0xE5, 0x8E, 0x26]).decode::<u32>() == 624485);
+        assert!(ILeb128::from_bytes(&[0xff, 0x7e]).decode::<i64>() == -129);
+        assert!(ILeb128::from_bytes(&[0xff, 0x7e]).try_decode::<i8>() ==
+                Err(Leb128Error::Overflow { target_bits: 8, offset: 1 }));
+    }
+
+    #[test]
+    fn test_read() {
+        let mut input: &[u8] = &[0xE5, 0x8E, 0x26, 0xff, 0x7e, 0x80, 2, 0x80];
+        assert!(::read_uleb128::<u32>(&mut input) == Ok(624485));
+        assert!(::read_sleb128::<i16>(&mut input) == Ok(-129));
+        assert!(input == [0x80, 2, 0x80]);
+        assert!(::read_uleb128::<u8>(&mut input) == Err(Leb128Error::Overflow { target_bits: 8, offset: 1 }));
+        assert!(input == [0x80, 2, 0x80]);
+        assert!(::read_uleb128::<u16>(&mut input) == Ok(256));
+        assert!(::read_uleb128::<u64>(&mut input) == Err(Leb128Error::Truncated { offset: 1 }));
+        assert!(input == [0x80]);
+    }
+
+    #[test]
+    fn test_split_first() {
+        let bytes = [0xff, 0x7e, 42];
+        let (number, rest) = ILeb128::split_first(&bytes).unwrap();
+        assert!(number == -129i32 && rest == [42]);
+        let (number, rest) = ULeb128::split_first(rest).unwrap();
+        assert!(number == 42u8 && rest.is_empty());
+        assert!(ULeb128::split_first(rest) == Err(Leb128Error::Truncated { offset: 0 }));
+    }
+
+    #[test]
+    fn test_overflow_policy() {
+        let saturating = DecodeOptions::new().overflow(OverflowPolicy::Saturating);
+        let wrapping = DecodeOptions::new().overflow(OverflowPolicy::Wrapping);
+        let big = ULeb128::from_bytes(&[0xac, 0x02]);
+        assert!(big.try_decode_with::<u8>(DecodeOptions::new()) ==
+                Err(Leb128Error::Overflow { target_bits: 8, offset: 1 }));
+        assert!(big.try_decode_with::<u8>(saturating) == Ok(255));
+        assert!(big.try_decode_with::<i8>(saturating) == Ok(127));
+        assert!(big.try_decode_with::<u8>(wrapping) == Ok(44));
+        assert!(big.try_decode_reporting::<u8>(wrapping) == Ok((44, true)));
+        assert!(big.try_decode_reporting::<u16>(wrapping) == Ok((300, false)));
+
+        let negative = ILeb128::from_bytes(&[0xff, 0x7e]);
+        assert!(negative.try_decode_with::<i8>(saturating) == Ok(-128));
+        assert!(negative.try_decode_with::<u32>(saturating) == Ok(0));
+        assert!(negative.try_decode_with::<i8>(wrapping) == Ok(127));
+        assert!(negative.try_decode_with::<u16>(wrapping) == Ok(65407));
+        assert!(ILeb128::from_bytes(&[0x40]).try_decode_reporting::<i128>(saturating) == Ok((-64, false)));
+
+        // Wrapping keeps the low bits of values which are too wide for u128.
+        let mut wide = [0xff; 19];
+        wide[18] = 0x7f;
+        assert!(ULeb128::from_bytes(&wide).try_decode_with::<u128>(wrapping) == Ok(u128::MAX));
+        assert!(ULeb128::from_bytes(&wide).try_decode_with::<i128>(saturating) == Ok(i128::MAX));
+    }
+
+    #[test]
+    fn test_decode_across_signedness() {
+        assert!(ULeb128::from_bytes(&[0xff, 0]).try_decode::<i8>() == Ok(127));
+        assert!(ULeb128::from_bytes(&[0x80, 1]).try_decode::<i8>() ==
+                Err(Leb128Error::Overflow { target_bits: 8, offset: 1 }));
+        assert!(ULeb128::from_bytes(&[0xff, 0xff, 0xff, 0xff, 0x07]).try_decode::<i32>() == Ok(i32::MAX));
+
+        assert!(ILeb128::from_bytes(&[0xff, 1]).try_decode::<u8>() == Ok(255));
+        assert!(ILeb128::from_bytes(&[0x7f]).try_decode::<u8>() ==
+                Err(Leb128Error::Overflow { target_bits: 8, offset: 0 }));
+        let mut max = vec![0xff; 18];
+        max.push(0x03);
+        assert!(ILeb128::from_bytes(&max).try_decode::<u128>() == Ok(u128::MAX));
+    }
+}
@@ -0,0 +1,156 @@
+//! Decoding LEB128 numbers from input which arrives in fragments.
+
+use core::marker::PhantomData;
+
+use decode::Partial;
+use zigzag::from_zigzag_bits;
+use {FromLeb128, Leb128Error, ZigZag};
+
+/// The result of feeding bytes to a `Leb128Decoder`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Decoded<T> {
+    /// All of the bytes were consumed without reaching the end of the number.
+    Incomplete,
+    /// The decoded number, and the number of bytes consumed from the last
+    /// call to `feed`.
+    Complete(T, usize),
+}
+
+/// A decoder for LEB128 numbers which are split across several buffers, e.g.,
+/// network packets.
+///
+/// Bytes are passed in with `feed` until the end of the number is reached;
+/// the decoder keeps the partly decoded value in between. Numbers which do
+/// not fit in `T` are reported with the same errors as `try_decode`, with
+/// offsets from the start of the number. After a number is complete, the
+/// decoder is ready to decode the next number.
+///
+/// After an error, `feed` returns the same error without consuming any bytes
+/// until `reset` is called. The error may be found part way through a number,
+/// so the bytes which follow it are not the start of the next number and the
+/// caller has to decide where to resume.
+#[derive(Debug, Clone)]
+pub struct Leb128Decoder<T> {
+    partial: Partial,
+    error: Option<Leb128Error>,
+    zigzag: bool,
+    marker: PhantomData<T>,
+}
+
+impl<T: FromLeb128> Leb128Decoder<T> {
+    /// A decoder for unsigned LEB128 numbers.
+    pub fn unsigned() -> Leb128Decoder<T> {
+        Leb128Decoder { partial: Partial::new::<T>(false), error: None, zigzag: false, marker: PhantomData }
+    }
+
+    /// A decoder for signed LEB128 numbers.
+    pub fn signed() -> Leb128Decoder<T> {
+        Leb128Decoder { partial: Partial::new::<T>(true), error: None, zigzag: false, marker: PhantomData }
+    }
+
+    /// Decode from the next bytes of the input. Bytes after the end of the
+    /// number are not consumed.
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<Decoded<T>, Leb128Error> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        for (i, &byte) in bytes.iter().enumerate() {
+            if let Err(e) = self.partial.push(byte) {
+                self.error = Some(e);
+                return Err(e);
+            }
+            if byte & 0b1000_0000 == 0 {
+                let bits = match self.partial.finish() {
+                    Ok(bits) => bits,
+                    Err(e) => {
+                        self.error = Some(e);
+                        return Err(e);
+                    }
+                };
+                self.partial.reset();
+                let bits = if self.zigzag { from_zigzag_bits(bits) } else { bits };
+                return Ok(Decoded::Complete(T::from_bits(bits), i + 1));
+            }
+        }
+        Ok(Decoded::Incomplete)
+    }
+
+    /// The number of bytes of the current number which have been consumed.
+    pub fn byte_count(&self) -> usize {
+        self.partial.count()
+    }
+
+    /// Discard any partly decoded number, and clear an error.
+    pub fn reset(&mut self) {
+        self.partial.reset();
+        self.error = None;
+    }
+}
+
+impl<T: ZigZag + FromLeb128> Leb128Decoder<T> {
+    /// A decoder for ZigZag-mapped unsigned LEB128 numbers. Numbers which do
+    /// not fit in the unsigned type of the same width as `T` are errors.
+    pub fn zigzag() -> Leb128Decoder<T> {
+        Leb128Decoder { partial: Partial::new::<T::Unsigned>(false), error: None, zigzag: true, marker: PhantomData }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_feed() {
+        let mut decoder = Leb128Decoder::<u32>::unsigned();
+        assert!(decoder.feed(&[0xE5]) == Ok(Decoded::Incomplete));
+        assert!(decoder.feed(&[]) == Ok(Decoded::Incomplete));
+        assert!(decoder.byte_count() == 1);
+        assert!(decoder.feed(&[0x8E, 0x26, 42]) == Ok(Decoded::Complete(624485, 2)));
+        assert!(decoder.byte_count() == 0);
+        assert!(decoder.feed(&[42]) == Ok(Decoded::Complete(42, 1)));
+
+        let mut decoder = Leb128Decoder::<i64>::signed();
+        assert!(decoder.feed(&[0xff]) == Ok(Decoded::Incomplete));
+        assert!(decoder.feed(&[0x7e]) == Ok(Decoded::Complete(-129, 1)));
+        let mut max = [0xff; 10];
+        max[9] = 0x01;
+        assert!(decoder.feed(&max[..4]) == Ok(Decoded::Incomplete));
+        assert!(decoder.feed(&max[4..]) == Err(Leb128Error::Overflow { target_bits: 64, offset: 9 }));
+    }
+
+    #[test]
+    fn test_feed_errors() {
+        let mut decoder = Leb128Decoder::<u8>::unsigned();
+        assert!(decoder.feed(&[0x80]) == Ok(Decoded::Incomplete));
+        assert!(decoder.feed(&[2]) == Err(Leb128Error::Overflow { target_bits: 8, offset: 1 }));
+        assert!(decoder.feed(&[1]) == Err(Leb128Error::Overflow { target_bits: 8, offset: 1 }));
+        decoder.reset();
+        assert!(decoder.feed(&[0x80]) == Ok(Decoded::Incomplete));
+        assert!(decoder.feed(&[0x80, 0]) == Err(Leb128Error::TooLong { max_bytes: 2, offset: 2 }));
+        // The rest of the over-long number is not decoded as a new number.
+        assert!(decoder.feed(&[0]) == Err(Leb128Error::TooLong { max_bytes: 2, offset: 2 }));
+        decoder.reset();
+        assert!(decoder.feed(&[0xff, 1]) == Ok(Decoded::Complete(255, 2)));
+
+        let mut decoder = Leb128Decoder::<i8>::signed();
+        assert!(decoder.feed(&[0xff, 0x7e]) == Err(Leb128Error::Overflow { target_bits: 8, offset: 1 }));
+        decoder.reset();
+        assert!(decoder.feed(&[0x80]) == Ok(Decoded::Incomplete));
+        decoder.reset();
+        assert!(decoder.feed(&[0x7f]) == Ok(Decoded::Complete(-1, 1)));
+    }
+
+    #[test]
+    fn test_feed_zigzag() {
+        let mut decoder = Leb128Decoder::<i16>::zigzag();
+        assert!(decoder.feed(&[0x03]) == Ok(Decoded::Complete(-2, 1)));
+        assert!(decoder.feed(&[0xff, 0xff]) == Ok(Decoded::Incomplete));
+        assert!(decoder.feed(&[0x03, 0]) == Ok(Decoded::Complete(i16::MIN, 1)));
+        assert!(decoder.feed(&[0xff, 0xff, 0x07]) == Err(Leb128Error::Overflow { target_bits: 16, offset: 2 }));
+
+        let mut decoder = Leb128Decoder::<i128>::zigzag();
+        let mut max = [0xff; 19];
+        max[18] = 0x03;
+        assert!(decoder.feed(&max) == Ok(Decoded::Complete(i128::MIN, 19)));
+    }
+}
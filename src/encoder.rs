@@ -0,0 +1,140 @@
+//! Encoding many LEB128 numbers into a single buffer.
+
+use alloc::vec::Vec;
+
+use {ToLeb128, ZigZag};
+
+macro_rules! push {
+    ($name: ident, $t: ty) => {
+        /// Append `value` and return the number of bytes written.
+        pub fn $name(&mut self, value: $t) -> usize {
+            self.push(value)
+        }
+    }
+}
+
+/// An encoder which appends LEB128 numbers to a growable buffer.
+///
+/// Unsigned types are encoded as unsigned LEB128 and signed types as signed
+/// LEB128. Values are encoded directly into the buffer, so there is no
+/// allocation per value, and the buffer can be reused with `clear`.
+#[derive(Debug, Clone, Default)]
+pub struct Leb128Encoder {
+    buf: Vec<u8>,
+}
+
+impl Leb128Encoder {
+    pub fn new() -> Leb128Encoder {
+        Leb128Encoder { buf: Vec::new() }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Leb128Encoder {
+        Leb128Encoder { buf: Vec::with_capacity(capacity) }
+    }
+
+    /// Append `value` and return the number of bytes written.
+    pub fn push<T: ToLeb128>(&mut self, value: T) -> usize {
+        let start = self.buf.len();
+        let count = value.leb128_len();
+        self.buf.resize(start + count, 0);
+        value.encode_leb128_to(&mut self.buf[start..]);
+        count
+    }
+
+    push!(push_u8, u8);
+    push!(push_u16, u16);
+    push!(push_u32, u32);
+    push!(push_u64, u64);
+    push!(push_u128, u128);
+    push!(push_usize, usize);
+    push!(push_i8, i8);
+    push!(push_i16, i16);
+    push!(push_i32, i32);
+    push!(push_i64, i64);
+    push!(push_i128, i128);
+    push!(push_isize, isize);
+
+    /// Append `value` as ZigZag-mapped unsigned LEB128 and return the number
+    /// of bytes written.
+    pub fn push_zigzag<T: ZigZag>(&mut self, value: T) -> usize {
+        self.push(value.to_zigzag())
+    }
+
+    /// Append every value of `values`.
+    pub fn extend<T: ToLeb128, I: IntoIterator<Item = T>>(&mut self, values: I) {
+        for value in values {
+            self.push(value);
+        }
+    }
+
+    /// Reserve exactly enough space to append every value of `values`.
+    pub fn reserve_for<T: ToLeb128, I: IntoIterator<Item = T>>(&mut self, values: I) {
+        let len = values.into_iter().map(T::leb128_len).sum();
+        self.buf.reserve(len);
+    }
+
+    /// The bytes encoded so far.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Remove the encoded bytes, keeping the buffer's capacity.
+    pub fn clear(&mut self) {
+        self.buf.clear();
+    }
+
+    /// Return the encoded bytes.
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use {ILeb128, ULeb128};
+
+    #[test]
+    fn test_push() {
+        let mut encoder = Leb128Encoder::new();
+        assert!(encoder.push_u32(624485) == 3);
+        assert!(encoder.push_i16(-129) == 2);
+        assert!(encoder.push(u128::MAX) == 19);
+        assert!(encoder.push_i8(-1) == 1);
+        let bytes = encoder.finish();
+        assert!(bytes[..5] == [0xE5, 0x8E, 0x26, 0xff, 0x7e]);
+        assert!(ULeb128::from_bytes(&bytes[5..]).expect_u128() == u128::MAX);
+        assert!(bytes[24..] == [0x7f]);
+
+        let mut encoder = Leb128Encoder::new();
+        assert!(encoder.push_zigzag(-64i32) == 1);
+        assert!(encoder.push_zigzag(i128::MIN) == 19);
+        assert!(encoder.as_bytes()[..2] == [0x7f, 0xff]);
+    }
+
+    #[test]
+    fn test_extend() {
+        let values = [0u64, 127, 128, 300, u64::MAX];
+        let mut encoder = Leb128Encoder::new();
+        encoder.reserve_for(values.iter().cloned());
+        assert!(encoder.buf.capacity() >= 1 + 1 + 2 + 2 + 10);
+        encoder.extend(values.iter().cloned());
+        assert!(encoder.len() == 16);
+        let decoded: Result<Vec<u64>, _> = ULeb128::iter(encoder.as_bytes()).decode(ULeb128::try_decode_u64).collect();
+        assert!(decoded.unwrap() == values);
+
+        encoder.clear();
+        assert!(encoder.is_empty());
+        encoder.extend(-65..65i32);
+        let decoded: Result<Vec<i32>, _> = ILeb128::iter(encoder.as_bytes()).decode(ILeb128::try_decode_i32).collect();
+        assert!(decoded.unwrap() == (-65..65).collect::<Vec<_>>());
+    }
+}
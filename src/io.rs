@@ -0,0 +1,178 @@
+//! Reading and writing LEB128 numbers using `std::io` streams.
+
+use core::mem;
+use core::slice;
+use std::io;
+
+use {EncodeILeb128, EncodeULeb128, ILeb128, Leb128Error, ULeb128, ZigZag, MAX_INT_BYTES};
+
+impl From<Leb128Error> for io::Error {
+    fn from(e: Leb128Error) -> io::Error {
+        let kind = match e {
+            Leb128Error::Truncated { .. } => io::ErrorKind::UnexpectedEof,
+            _ => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, e)
+    }
+}
+
+// Read the bytes of a single LEB128 number into `buf`, returning the number of
+// bytes read. Never reads more than `buf.len()` bytes.
+fn read_bytes<R: io::Read + ?Sized>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let max_bytes = buf.len();
+    for (i, byte) in buf.iter_mut().enumerate() {
+        if let Err(e) = reader.read_exact(slice::from_mut(byte)) {
+            if e.kind() == io::ErrorKind::UnexpectedEof {
+                return Err(Leb128Error::Truncated { offset: i }.into());
+            }
+            return Err(e);
+        }
+        if *byte & 0b1000_0000 == 0 {
+            return Ok(i + 1);
+        }
+    }
+    Err(Leb128Error::TooLong { max_bytes, offset: max_bytes }.into())
+}
+
+macro_rules! read_leb128 {
+    ($name: ident, $leb_t: ident, $decode: ident, $t: ty) => {
+        fn $name(&mut self) -> io::Result<$t> {
+            let mut buf = [0; MAX_INT_BYTES];
+            let max_bytes = (mem::size_of::<$t>() * 8).div_ceil(7);
+            let len = read_bytes(self, &mut buf[..max_bytes])?;
+            Ok($leb_t(&buf[..len]).$decode()?)
+        }
+    }
+}
+
+/// Extension methods for reading LEB128 numbers from any `io::Read`.
+///
+/// Each method consumes exactly the bytes of one number, reading a byte at a
+/// time, so wrap unbuffered readers in a `BufReader`. Input which ends before
+/// the last byte of a number is reported as `io::ErrorKind::UnexpectedEof`.
+/// Numbers which do not fit in the target type are reported as
+/// `io::ErrorKind::InvalidData`; at most the longest valid encoding for the
+/// target type is read before giving up.
+pub trait ReadLeb128Ext: io::Read {
+    read_leb128!(read_uleb128_u8, ULeb128, try_decode_u8, u8);
+    read_leb128!(read_uleb128_u16, ULeb128, try_decode_u16, u16);
+    read_leb128!(read_uleb128_u32, ULeb128, try_decode_u32, u32);
+    read_leb128!(read_uleb128_u64, ULeb128, try_decode_u64, u64);
+    read_leb128!(read_uleb128_u128, ULeb128, try_decode_u128, u128);
+    read_leb128!(read_uleb128_usize, ULeb128, try_decode_usize, usize);
+
+    read_leb128!(read_sleb128_i8, ILeb128, try_decode_i8, i8);
+    read_leb128!(read_sleb128_i16, ILeb128, try_decode_i16, i16);
+    read_leb128!(read_sleb128_i32, ILeb128, try_decode_i32, i32);
+    read_leb128!(read_sleb128_i64, ILeb128, try_decode_i64, i64);
+    read_leb128!(read_sleb128_i128, ILeb128, try_decode_i128, i128);
+    read_leb128!(read_sleb128_isize, ILeb128, try_decode_isize, isize);
+
+    /// Read a ZigZag-mapped unsigned LEB128 number, e.g.,
+    /// `reader.read_zigzag::<i32>()`.
+    fn read_zigzag<T: ZigZag>(&mut self) -> io::Result<T> {
+        let mut buf = [0; MAX_INT_BYTES];
+        let max_bytes = <T::Unsigned as EncodeULeb128>::MAX_BYTES;
+        let len = read_bytes(self, &mut buf[..max_bytes])?;
+        Ok(ULeb128(&buf[..len]).try_decode_zigzag()?)
+    }
+}
+
+impl<R: io::Read + ?Sized> ReadLeb128Ext for R {}
+
+/// Extension methods for writing LEB128 numbers to any `io::Write`.
+///
+/// Values are encoded on the stack and written with a single `write_all`, so
+/// there is no heap allocation.
+pub trait WriteLeb128Ext: io::Write {
+    /// Write `value` as unsigned LEB128 and return the number of bytes written.
+    fn write_uleb128<T: EncodeULeb128>(&mut self, value: T) -> io::Result<usize> {
+        let (bytes, count) = value.encode_uleb128_array();
+        self.write_all(&bytes.as_ref()[..count])?;
+        Ok(count)
+    }
+
+    /// Write `value` as signed LEB128 and return the number of bytes written.
+    fn write_sleb128<T: EncodeILeb128>(&mut self, value: T) -> io::Result<usize> {
+        let (bytes, count) = value.encode_ileb128_array();
+        self.write_all(&bytes.as_ref()[..count])?;
+        Ok(count)
+    }
+
+    /// Write `value` as ZigZag-mapped unsigned LEB128 and return the number
+    /// of bytes written.
+    fn write_zigzag<T: ZigZag>(&mut self, value: T) -> io::Result<usize> {
+        self.write_uleb128(value.to_zigzag())
+    }
+}
+
+impl<W: io::Write + ?Sized> WriteLeb128Ext for W {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn test_read_unsigned() {
+        let mut input = Cursor::new(vec![0xE5, 0x8E, 0x26, 42, 255, 1, 7]);
+        assert!(input.read_uleb128_u64().unwrap() == 624485);
+        assert!(input.read_uleb128_u32().unwrap() == 42);
+        assert!(input.read_uleb128_u8().unwrap() == 255);
+        assert!(input.position() == 6);
+
+        let mut rest = vec![];
+        input.read_to_end(&mut rest).unwrap();
+        assert!(rest == vec![7]);
+    }
+
+    #[test]
+    fn test_read_signed() {
+        let mut input = Cursor::new(vec![0x7e, 0xff, 0x7e, 0x80, 0x7f]);
+        assert!(input.read_sleb128_i32().unwrap() == -2);
+        assert!(input.read_sleb128_i16().unwrap() == -129);
+        assert!(input.read_sleb128_i8().unwrap() == -128);
+        assert!(input.position() == 5);
+
+        let mut max = vec![0xff; 18];
+        max.push(0x01);
+        assert!(Cursor::new(max).read_sleb128_i128().unwrap() == i128::MAX);
+    }
+
+    #[test]
+    fn test_read_errors() {
+        let e = Cursor::new(vec![0x80, 0x80]).read_uleb128_u64().unwrap_err();
+        assert!(e.kind() == io::ErrorKind::UnexpectedEof);
+
+        let e = Cursor::new(vec![128, 2]).read_sleb128_i8().unwrap_err();
+        assert!(e.kind() == io::ErrorKind::InvalidData);
+
+        // Stops after the longest valid encoding of a u32.
+        let mut input = Cursor::new(vec![0x80; 100]);
+        let e = input.read_uleb128_u32().unwrap_err();
+        assert!(e.kind() == io::ErrorKind::InvalidData);
+        assert!(input.position() == 5);
+    }
+
+    #[test]
+    fn test_write() {
+        let mut output = vec![];
+        assert!(output.write_uleb128(624485u32).unwrap() == 3);
+        assert!(output.write_uleb128(0u8).unwrap() == 1);
+        assert!(output.write_sleb128(-129i16).unwrap() == 2);
+        assert!(output.write_uleb128(u128::MAX).unwrap() == 19);
+        assert!(output.write_sleb128(i64::MIN).unwrap() == 10);
+        assert!(output.write_zigzag(-2i32).unwrap() == 1);
+        assert!(output.write_zigzag(i128::MIN).unwrap() == 19);
+
+        let mut input = Cursor::new(output);
+        assert!(input.read_uleb128_u32().unwrap() == 624485);
+        assert!(input.read_uleb128_u8().unwrap() == 0);
+        assert!(input.read_sleb128_i16().unwrap() == -129);
+        assert!(input.read_uleb128_u128().unwrap() == u128::MAX);
+        assert!(input.read_sleb128_i64().unwrap() == i64::MIN);
+        assert!(input.read_zigzag::<i32>().unwrap() == -2);
+        assert!(input.read_zigzag::<i128>().unwrap() == i128::MIN);
+        assert!(input.read_uleb128_u8().is_err());
+    }
+}
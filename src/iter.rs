@@ -0,0 +1,194 @@
+//! Lazy iteration over a buffer of LEB128 numbers.
+
+use {DecodeOptions, Leb128Error};
+
+/// An iterator over the LEB128 numbers in a byte slice, created by
+/// `ULeb128::iter` or `ILeb128::iter`.
+///
+/// Yields borrowed numbers without allocating. If the buffer ends part way
+/// through a number, the last item is a `Leb128Error::Truncated` error and
+/// the incomplete bytes are left in `remaining`. Likewise if a limit set with
+/// `with_options` is exceeded.
+#[derive(Debug, Clone)]
+pub struct Leb128Iter<'a, T> {
+    bytes: &'a [u8],
+    offset: usize,
+    count: usize,
+    done: bool,
+    options: DecodeOptions,
+    wrap: fn(&'a [u8]) -> T,
+}
+
+impl<'a, T> Leb128Iter<'a, T> {
+    pub(crate) fn new(bytes: &'a [u8], wrap: fn(&'a [u8]) -> T) -> Leb128Iter<'a, T> {
+        Leb128Iter { bytes, offset: 0, count: 0, done: false, options: DecodeOptions::new(), wrap }
+    }
+
+    /// Limit the length of each number, the number of numbers and the total
+    /// number of bytes read, as set in `options`.
+    pub fn with_options(mut self, options: DecodeOptions) -> Leb128Iter<'a, T> {
+        self.options = options;
+        self
+    }
+
+    /// The bytes which have not yet been parsed.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// The offset of the next number from the start of the buffer.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Decode each number using `decode`, e.g., `ULeb128::try_decode_u64`.
+    /// Errors are reported with offsets from the start of the buffer.
+    pub fn decode<V>(self, decode: fn(T) -> Result<V, Leb128Error>) -> DecodeIter<'a, T, V> {
+        DecodeIter { iter: self, decode }
+    }
+}
+
+impl<'a, T> Iterator for Leb128Iter<'a, T> {
+    type Item = Result<T, Leb128Error>;
+
+    fn next(&mut self) -> Option<Result<T, Leb128Error>> {
+        if self.done || self.bytes.is_empty() {
+            return None;
+        }
+        if let Some(max_count) = self.options.max_count {
+            if self.count >= max_count {
+                self.done = true;
+                return Some(Err(Leb128Error::TooMany { max_count, offset: self.offset }));
+            }
+        }
+
+        // Only look for the end of the number as far as the limits allow.
+        let mut len = self.bytes.len();
+        let mut error = Leb128Error::Truncated { offset: self.offset + len };
+        if let Some(max_bytes) = self.options.max_bytes {
+            if max_bytes < len {
+                len = max_bytes;
+                error = Leb128Error::TooLong { max_bytes, offset: self.offset + len };
+            }
+        }
+        if let Some(max_total_bytes) = self.options.max_total_bytes {
+            let budget = max_total_bytes.saturating_sub(self.offset);
+            if budget < len {
+                len = budget;
+                error = Leb128Error::OverBudget { max_total_bytes, offset: self.offset + len };
+            }
+        }
+
+        match self.bytes[..len].iter().position(|byte| byte & 0b1000_0000 == 0) {
+            Some(end) => {
+                let (number, rest) = self.bytes.split_at(end + 1);
+                self.bytes = rest;
+                self.offset += number.len();
+                self.count += 1;
+                Some(Ok((self.wrap)(number)))
+            }
+            None => {
+                // Leave the incomplete bytes for `remaining`.
+                self.done = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+/// An iterator which decodes each number of a `Leb128Iter`, created by
+/// `Leb128Iter::decode`.
+#[derive(Debug, Clone)]
+pub struct DecodeIter<'a, T, V> {
+    iter: Leb128Iter<'a, T>,
+    decode: fn(T) -> Result<V, Leb128Error>,
+}
+
+impl<'a, T, V> DecodeIter<'a, T, V> {
+    /// The bytes which have not yet been parsed.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.iter.remaining()
+    }
+}
+
+impl<'a, T, V> Iterator for DecodeIter<'a, T, V> {
+    type Item = Result<V, Leb128Error>;
+
+    fn next(&mut self) -> Option<Result<V, Leb128Error>> {
+        let offset = self.iter.offset();
+        self.iter.next().map(|result| {
+            result.and_then(|number| (self.decode)(number).map_err(|e| e.offset_by(offset)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::vec::Vec;
+    use {DecodeOptions, ILeb128, Leb128Error, ULeb128};
+
+    #[test]
+    fn test_iter() {
+        let bytes = [0xE5, 0x8E, 0x26, 42, 128, 1];
+        let mut iter = ULeb128::iter(&bytes);
+        assert!(iter.next().unwrap().unwrap().expect_u32() == 624485);
+        assert!(iter.offset() == 3);
+        assert!(iter.remaining() == [42, 128, 1]);
+        assert!(iter.next().unwrap().unwrap().expect_u32() == 42);
+        assert!(iter.next().unwrap().unwrap().expect_u32() == 128);
+        assert!(iter.next().is_none());
+        assert!(iter.remaining().is_empty());
+
+        assert!(ILeb128::iter(&[]).next().is_none());
+    }
+
+    #[test]
+    fn test_iter_truncated() {
+        let bytes = [0x7e, 0xff, 0x7e, 0x80, 0x80];
+        let mut iter = ILeb128::iter(&bytes);
+        assert!(iter.next().unwrap().unwrap().expect_i32() == -2);
+        assert!(iter.next().unwrap().unwrap().expect_i32() == -129);
+        assert!(iter.next().unwrap() == Err(Leb128Error::Truncated { offset: 5 }));
+        assert!(iter.next().is_none());
+        assert!(iter.remaining() == [0x80, 0x80]);
+        assert!(iter.offset() == 3);
+    }
+
+    #[test]
+    fn test_iter_limits() {
+        let bytes = [1, 0x80, 0x80, 0x80, 0, 2, 3];
+        let mut iter = ULeb128::iter(&bytes).with_options(DecodeOptions::new().max_bytes(3));
+        assert!(iter.next().unwrap().unwrap() == 1u8);
+        assert!(iter.next().unwrap() == Err(Leb128Error::TooLong { max_bytes: 3, offset: 4 }));
+        assert!(iter.next().is_none());
+        assert!(iter.remaining() == [0x80, 0x80, 0x80, 0, 2, 3]);
+
+        let count = |bytes: &[u8], options| {
+            ULeb128::iter(bytes).with_options(options).collect::<Result<Vec<_>, _>>().map(|v| v.len())
+        };
+        let options = DecodeOptions::new().max_count(2);
+        assert!(count(&[1, 2], options) == Ok(2));
+        assert!(count(&[1, 2, 3], options) == Err(Leb128Error::TooMany { max_count: 2, offset: 2 }));
+
+        let options = DecodeOptions::new().max_total_bytes(5);
+        assert!(count(&bytes[..5], options) == Ok(2));
+        assert!(count(&bytes, options) == Err(Leb128Error::OverBudget { max_total_bytes: 5, offset: 5 }));
+        let mut iter = ULeb128::iter(&[0xff, 0xff, 0x7f]).with_options(options.max_bytes(2));
+        assert!(iter.next().unwrap() == Err(Leb128Error::TooLong { max_bytes: 2, offset: 2 }));
+    }
+
+    #[test]
+    fn test_decode() {
+        let bytes = [42, 128, 2, 255, 1, 128];
+        let mut iter = ULeb128::iter(&bytes).decode(ULeb128::try_decode_u8);
+        assert!(iter.next() == Some(Ok(42)));
+        assert!(iter.next() == Some(Err(Leb128Error::Overflow { target_bits: 8, offset: 2 })));
+        assert!(iter.next() == Some(Ok(255)));
+        assert!(iter.next() == Some(Err(Leb128Error::Truncated { offset: 6 })));
+        assert!(iter.next().is_none());
+        assert!(iter.remaining() == [128]);
+
+        let values: Result<Vec<i64>, _> = ILeb128::iter(&[0x7e, 0x80, 0x7f]).decode(ILeb128::try_decode_i64).collect();
+        assert!(values == Ok(vec![-2, -128]));
+    }
+}
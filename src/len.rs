@@ -0,0 +1,64 @@
+//! Computing the length of an encoding without encoding, e.g., for size
+//! headers and offset tables. All of these functions are `const`.
+
+/// The number of bytes in the unsigned LEB128 encoding of `value`.
+pub const fn uleb128_len(value: u128) -> usize {
+    let bit_count = 128 - value.leading_zeros() as usize;
+    if bit_count == 0 { 1 } else { bit_count.div_ceil(7) }
+}
+
+/// The number of bytes in the signed LEB128 encoding of `value`.
+pub const fn sleb128_len(value: i128) -> usize {
+    // Count the significant bits, plus one for the sign.
+    let magnitude = value ^ (value >> 127);
+    (128 - magnitude.leading_zeros() as usize + 1).div_ceil(7)
+}
+
+macro_rules! len {
+    ($name: ident, $len: ident, $t: ty, $wide: ty) => {
+        /// The number of bytes in the LEB128 encoding of `value`.
+        pub const fn $name(value: $t) -> usize {
+            $len(value as $wide)
+        }
+    }
+}
+
+len!(uleb128_len_u8, uleb128_len, u8, u128);
+len!(uleb128_len_u16, uleb128_len, u16, u128);
+len!(uleb128_len_u32, uleb128_len, u32, u128);
+len!(uleb128_len_u64, uleb128_len, u64, u128);
+len!(uleb128_len_usize, uleb128_len, usize, u128);
+len!(sleb128_len_i8, sleb128_len, i8, i128);
+len!(sleb128_len_i16, sleb128_len, i16, i128);
+len!(sleb128_len_i32, sleb128_len, i32, i128);
+len!(sleb128_len_i64, sleb128_len, i64, i128);
+len!(sleb128_len_isize, sleb128_len, isize, i128);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use {EncodeILeb128, EncodeULeb128};
+
+    // Usable in const contexts, e.g., for the size of a header.
+    const HEADER: [u8; uleb128_len_u32(624485)] = [0; 3];
+
+    #[test]
+    fn test_uleb128_len() {
+        assert!(HEADER.len() == 3);
+        for &value in &[0u64, 1, 127, 128, 16383, 16384, u64::MAX] {
+            assert!(uleb128_len_u64(value) == value.encode_uleb128_array().1);
+        }
+        assert!(uleb128_len_u8(255) == 2);
+        assert!(uleb128_len(u128::MAX) == 19);
+    }
+
+    #[test]
+    fn test_sleb128_len() {
+        for &value in &[0i64, -1, 63, 64, -64, -65, 8191, -8193, i64::MIN, i64::MAX] {
+            assert!(sleb128_len_i64(value) == value.encode_ileb128_array().1);
+        }
+        assert!(sleb128_len_i8(-128) == 2);
+        assert!(sleb128_len(i128::MIN) == 19);
+        assert!(sleb128_len(i128::MAX) == 19);
+    }
+}
@@ -7,247 +7,1101 @@
 //!
 //! We support encoding and decoding to all Rust integer types and to arrays of
 //! bytes.
-
-#![feature(core_intrinsics)]
-use std::mem;
-
-/// Signed LEB128 integer.
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
-pub struct ILeb128Owned(Vec<u8>);
-
-/// Unsigned LEB128 integer.
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
-pub struct ULeb128Owned(Vec<u8>);
+//!
+//! The crate is `no_std`. The borrowed types and all decoding to integer types
+//! are always available; the heap-allocated types and everything else which
+//! needs a `Vec` require the `alloc` feature. The `std` feature (enabled by
+//! default) implies `alloc` and adds `std::error::Error` implementations.
+//!
+//! # Equality and hashing
+//!
+//! Equality, ordering and hashing are by numeric value, not by bytes, so
+//! non-canonical encodings such as `[0x80, 0x00]` and `[0x00]` compare and
+//! hash equal. Owned and borrowed numbers can be compared with each other and
+//! with native integers.
+//!
+//! # Owned and borrowed numbers
+//!
+//! `as_leb128` borrows an owned number and `into_owned` copies a borrowed one.
+//! Both give their encoded bytes through `Deref<Target = [u8]>` and
+//! `AsRef<[u8]>`.
+//!
+//! The owned types do not implement `Borrow` for the borrowed types, so a
+//! `Cow<ULeb128>` cannot hold a `ULeb128Owned`, and a `HashMap` with owned keys
+//! cannot be looked up by a borrowed number. These need an unsized borrowed
+//! type, which cannot be made from a byte slice without `unsafe` code, and the
+//! crate has none. Look up with `number.into_owned()` instead, which does not
+//! allocate for numbers which fit in an integer type.
+
+#![no_std]
+
+#[cfg(any(feature = "std", test))]
+#[cfg_attr(test, macro_use)]
+extern crate std;
+#[cfg(feature = "alloc")]
+#[macro_use]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
+use core::convert::TryFrom;
+use core::fmt;
+use core::mem;
+use core::ops::Deref;
+use decode::sealed;
+#[cfg(feature = "alloc")]
+use storage::Storage;
+
+mod cmp;
+mod constant;
+mod cursor;
+mod decode;
+mod decoder;
+#[cfg(feature = "alloc")]
+mod encoder;
+#[cfg(feature = "std")]
+mod io;
+mod iter;
+mod len;
+#[cfg(feature = "alloc")]
+mod storage;
+mod zigzag;
+
+#[cfg(feature = "std")]
+pub use io::{ReadLeb128Ext, WriteLeb128Ext};
+pub use constant::{decode_sleb128_const, decode_uleb128_const, sleb128_array, uleb128_array};
+pub use cursor::Leb128Cursor;
+pub use decode::{read_sleb128, read_uleb128, DecodeOptions, FromLeb128, OverflowPolicy};
+pub use decoder::{Decoded, Leb128Decoder};
+#[cfg(feature = "alloc")]
+pub use encoder::Leb128Encoder;
+pub use iter::{DecodeIter, Leb128Iter};
+pub use len::{sleb128_len, sleb128_len_i16, sleb128_len_i32, sleb128_len_i64, sleb128_len_i8, sleb128_len_isize};
+pub use len::{uleb128_len, uleb128_len_u16, uleb128_len_u32, uleb128_len_u64, uleb128_len_u8, uleb128_len_usize};
+pub use zigzag::{read_zigzag, ZigZag};
+
+/// Signed LEB128 integer. The encoding of any integer type is stored inline,
+/// without allocating.
+///
+/// Compares and hashes by value, see the crate's "Equality and hashing" docs.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub struct ILeb128Owned(Storage);
+
+/// Unsigned LEB128 integer. The encoding of any integer type is stored inline,
+/// without allocating.
+///
+/// Compares and hashes by value, see the crate's "Equality and hashing" docs.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub struct ULeb128Owned(Storage);
 
 /// Signed LEB128 integer, backed by a reference.
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+///
+/// Compares and hashes by value, see the crate's "Equality and hashing" docs.
+#[derive(Debug, Copy, Clone)]
 pub struct ILeb128<'a>(&'a [u8]);
 
 /// Unsigned LEB128 integer, backed by a reference.
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+///
+/// Compares and hashes by value, see the crate's "Equality and hashing" docs.
+#[derive(Debug, Copy, Clone)]
 pub struct ULeb128<'a>(&'a [u8]);
 
-// TODO PartialEq to compare Ref with non-Ref versions
+/// An error encountered while decoding a LEB128 number.
+///
+/// Every variant records the offset of the byte at which the problem was
+/// found, relative to the start of the input passed to the decoding function.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Leb128Error {
+    /// The input ended before the last byte of a number. The offset is the
+    /// length of the input.
+    Truncated { offset: usize },
+    /// The number has too many significant bits for an integer of
+    /// `target_bits` bits.
+    Overflow { target_bits: u32, offset: usize },
+    /// The number is not encoded using the minimum number of bytes.
+    NonCanonical { offset: usize },
+    /// The number is encoded using more than `max_bytes` bytes.
+    TooLong { max_bytes: usize, offset: usize },
+    /// The input contains more than `max_count` numbers.
+    TooMany { max_count: usize, offset: usize },
+    /// The numbers in the input use more than `max_total_bytes` bytes.
+    OverBudget { max_total_bytes: usize, offset: usize },
+    /// The number is negative, but a non-negative number is needed. The
+    /// offset is that of the last byte, which holds the sign.
+    Negative { offset: usize },
+}
+
+impl Leb128Error {
+    /// The offset of the byte at which the error was found.
+    pub fn offset(&self) -> usize {
+        match *self {
+            Leb128Error::Truncated { offset } |
+            Leb128Error::Overflow { offset, .. } |
+            Leb128Error::NonCanonical { offset } |
+            Leb128Error::TooLong { offset, .. } |
+            Leb128Error::TooMany { offset, .. } |
+            Leb128Error::OverBudget { offset, .. } |
+            Leb128Error::Negative { offset } => offset,
+        }
+    }
 
+    // The same error, with the offset moved on by `base` bytes.
+    fn offset_by(self, base: usize) -> Leb128Error {
+        match self {
+            Leb128Error::Truncated { offset } => {
+                Leb128Error::Truncated { offset: offset + base }
+            }
+            Leb128Error::Overflow { target_bits, offset } => {
+                Leb128Error::Overflow { target_bits, offset: offset + base }
+            }
+            Leb128Error::NonCanonical { offset } => {
+                Leb128Error::NonCanonical { offset: offset + base }
+            }
+            Leb128Error::TooLong { max_bytes, offset } => {
+                Leb128Error::TooLong { max_bytes, offset: offset + base }
+            }
+            Leb128Error::TooMany { max_count, offset } => {
+                Leb128Error::TooMany { max_count, offset: offset + base }
+            }
+            Leb128Error::OverBudget { max_total_bytes, offset } => {
+                Leb128Error::OverBudget { max_total_bytes, offset: offset + base }
+            }
+            Leb128Error::Negative { offset } => {
+                Leb128Error::Negative { offset: offset + base }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Leb128Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Leb128Error::Truncated { offset } => {
+                write!(f, "truncated LEB128 number at offset {}", offset)
+            }
+            Leb128Error::Overflow { target_bits, offset } => {
+                write!(f, "LEB128 number overflows {}-bit integer at offset {}", target_bits, offset)
+            }
+            Leb128Error::NonCanonical { offset } => {
+                write!(f, "non-canonical LEB128 number at offset {}", offset)
+            }
+            Leb128Error::TooLong { max_bytes, offset } => {
+                write!(f, "LEB128 number longer than {} byte(s) at offset {}", max_bytes, offset)
+            }
+            Leb128Error::TooMany { max_count, offset } => {
+                write!(f, "more than {} LEB128 number(s) at offset {}", max_count, offset)
+            }
+            Leb128Error::OverBudget { max_total_bytes, offset } => {
+                write!(f, "LEB128 numbers longer than {} byte(s) in total at offset {}", max_total_bytes, offset)
+            }
+            Leb128Error::Negative { offset } => {
+                write!(f, "negative LEB128 number at offset {}", offset)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Leb128Error {}
+
+/// An error encountered while encoding a LEB128 number.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum EncodeError {
+    /// The output buffer is shorter than the `needed` bytes of the encoding.
+    BufferTooSmall { needed: usize },
+    /// The value does not fit in an integer of `bits` bits.
+    OutOfRange { bits: u32 },
+    /// The slot is longer than the `max_bytes` bytes which a number of the
+    /// type may use, so it would not decode to the type.
+    SlotTooLong { max_bytes: usize },
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EncodeError::BufferTooSmall { needed } => {
+                write!(f, "buffer too small for LEB128 number, {} byte(s) needed", needed)
+            }
+            EncodeError::OutOfRange { bits } => {
+                write!(f, "value does not fit in a {}-bit integer", bits)
+            }
+            EncodeError::SlotTooLong { max_bytes } => {
+                write!(f, "LEB128 slot longer than {} byte(s)", max_bytes)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EncodeError {}
+
+#[cfg(feature = "alloc")]
 macro_rules! dispatch {
     ($name: ident, $typ: ty) => {
         pub fn $name(&self) -> $typ {
-            self.as_ref().$name()
+            self.as_leb128().$name()
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+macro_rules! owned_impl {
+    ($t: ident, $owned_t: ident) => {
+        pub fn from_bytes(bytes: &[u8]) -> $owned_t {
+            $t::from_bytes(bytes).into_owned()
+        }
+
+        /// The length of the number is not limited, see `try_from_bytes` and
+        /// `try_from_bytes_with` on the borrowed type.
+        pub fn try_from_bytes(bytes: &[u8]) -> Result<$owned_t, Leb128Error> {
+            $t::try_from_bytes(bytes).map(|i| i.into_owned())
+        }
+
+        pub fn try_from_bytes_strict(bytes: &[u8]) -> Result<$owned_t, Leb128Error> {
+            $t::try_from_bytes_strict(bytes).map(|i| i.into_owned())
+        }
+
+        pub fn all_from_bytes(bytes: &[u8]) -> Vec<$owned_t> {
+            match $owned_t::try_all_from_bytes(bytes) {
+                Ok(result) => result,
+                Err(_) => panic!("all_from_bytes on invalid input"),
+            }
+        }
+
+        /// Neither the length nor the number of numbers is limited, use
+        /// `try_all_from_bytes_with` for untrusted input.
+        pub fn try_all_from_bytes(bytes: &[u8]) -> Result<Vec<$owned_t>, Leb128Error> {
+            $t::iter(bytes).map(|i| i.map($t::into_owned)).collect()
+        }
+
+        pub fn try_all_from_bytes_with(bytes: &[u8], options: DecodeOptions) -> Result<Vec<$owned_t>, Leb128Error> {
+            $t::iter(bytes).with_options(options).map(|i| i.map($t::into_owned)).collect()
+        }
+
+        pub fn byte_count(self) -> usize {
+            self.0.len()
+        }
+
+        /// Borrow as the borrowed type, e.g., to pass to functions which take
+        /// a borrowed number.
+        pub fn as_leb128(&self) -> $t<'_> {
+            $t(&self.0)
+        }
+
+        /// Decode to any integer type, see `decode` on the borrowed type.
+        pub fn decode<T: FromLeb128>(&self) -> T {
+            self.as_leb128().decode()
+        }
+
+        pub fn try_decode<T: FromLeb128>(&self) -> Result<T, Leb128Error> {
+            self.as_leb128().try_decode()
+        }
+
+        pub fn try_decode_with<T: FromLeb128>(&self, options: DecodeOptions) -> Result<T, Leb128Error> {
+            self.as_leb128().try_decode_with(options)
+        }
+
+        pub fn try_decode_reporting<T: FromLeb128>(&self, options: DecodeOptions) -> Result<(T, bool), Leb128Error> {
+            self.as_leb128().try_decode_reporting(options)
+        }
+    }
+}
+
+// Access to the encoded bytes of a number, without copying. There is no
+// `Borrow`, see the crate docs; `Borrow<[u8]>` would also not be consistent
+// with hashing by value.
+macro_rules! impl_bytes {
+    ($t: ty) => {
+        impl<'a> AsRef<[u8]> for $t {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl<'a> Deref for $t {
+            type Target = [u8];
+
+            fn deref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+    }
+}
+
+impl_bytes!(ULeb128<'a>);
+impl_bytes!(ILeb128<'a>);
+#[cfg(feature = "alloc")]
+impl_bytes!(ULeb128Owned);
+#[cfg(feature = "alloc")]
+impl_bytes!(ILeb128Owned);
+
+#[cfg(feature = "alloc")]
+macro_rules! impl_from_borrowed {
+    ($t: ident, $owned_t: ident) => {
+        impl<'a> From<$t<'a>> for $owned_t {
+            fn from(number: $t<'a>) -> $owned_t {
+                number.into_owned()
+            }
+        }
+
+        impl<'a> From<&'a $owned_t> for $t<'a> {
+            fn from(number: &'a $owned_t) -> $t<'a> {
+                number.as_leb128()
+            }
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl_from_borrowed!(ULeb128, ULeb128Owned);
+#[cfg(feature = "alloc")]
+impl_from_borrowed!(ILeb128, ILeb128Owned);
+
+/// Reinterprets a non-negative signed number as unsigned, or returns
+/// `Leb128Error::Negative`.
+#[cfg(feature = "alloc")]
+impl TryFrom<ILeb128Owned> for ULeb128Owned {
+    type Error = Leb128Error;
+
+    fn try_from(number: ILeb128Owned) -> Result<ULeb128Owned, Leb128Error> {
+        let last = number.0.len() - 1;
+        if number.0[last] & 0b0100_0000 != 0 {
+            return Err(Leb128Error::Negative { offset: last });
+        }
+        // The groups of a non-negative number are the same, but the unsigned
+        // number may not need the top group for the sign.
+        Ok(ULeb128(&number.0).canonicalize())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<ULeb128Owned> for ILeb128Owned {
+    fn from(number: ULeb128Owned) -> ILeb128Owned {
+        let len = ULeb128(&number.0).canonical_len();
+        let mut bytes = number.0[..len].to_vec();
+        bytes[len - 1] &= 0b0111_1111;
+        // Add a group for the sign if the top group looks negative.
+        if bytes[len - 1] & 0b0100_0000 != 0 {
+            bytes[len - 1] |= 0b1000_0000;
+            bytes.push(0);
         }
+        ILeb128Owned(Storage::from_vec(bytes))
     }
 }
 
+#[cfg(feature = "alloc")]
 impl ILeb128Owned {
-    pub fn from_bytes(bytes: &[u8]) -> ILeb128Owned {
-        ILeb128::from_bytes(bytes).to_owned()
+    owned_impl!(ILeb128, ILeb128Owned);
+
+    /// Encode `value` using exactly `width` bytes, so that the slot can be
+    /// overwritten later with `ILeb128::patch_in_place`. Returns an error if
+    /// `value` does not fit in `width` bytes, or `width` is more than
+    /// `T::MAX_BYTES`.
+    pub fn encode_padded<T: EncodeILeb128>(value: T, width: usize) -> Result<ILeb128Owned, EncodeError> {
+        let mut bytes = Storage::zeroed(width);
+        ILeb128::patch_in_place(&mut bytes, value)?;
+        Ok(ILeb128Owned(bytes))
+    }
+
+    /// Encode `value`, which must fit in a signed integer of `N` bits.
+    pub fn encode_bits<const N: u32>(value: i128) -> Result<ILeb128Owned, EncodeError> {
+        let mut bytes = Storage::zeroed(sleb128_len(value));
+        ILeb128::encode_bits_to::<N>(value, &mut bytes)?;
+        Ok(ILeb128Owned(bytes))
+    }
+
+    pub fn decode_bits<const N: u32>(&self) -> i128 {
+        self.as_leb128().decode_bits::<N>()
     }
 
-    pub fn all_from_bytes(bytes: &[u8]) -> Vec<ILeb128Owned> {
-        ILeb128::all_from_bytes(bytes).into_iter().map(|i| i.to_owned()).collect()
+    pub fn try_decode_bits<const N: u32>(&self) -> Result<i128, Leb128Error> {
+        self.as_leb128().try_decode_bits::<N>()
     }
 
-    pub fn byte_count(self) -> usize {
-        self.0.len()
+    /// Encode the `N` bit pattern `value` as a signed integer of `N` bits.
+    pub fn encode_unsigned_bits<const N: u32>(value: u128) -> Result<ILeb128Owned, EncodeError> {
+        let mut bytes = [0; MAX_INT_BYTES];
+        let count = ILeb128::encode_unsigned_bits_to::<N>(value, &mut bytes)?;
+        Ok(ILeb128Owned(Storage::from_slice(&bytes[..count])))
     }
 
-    pub fn as_ref(&self) -> ILeb128 {
-        ILeb128(&self.0)
+    pub fn try_decode_unsigned_bits<const N: u32>(&self) -> Result<u128, Leb128Error> {
+        self.as_leb128().try_decode_unsigned_bits::<N>()
     }
 
+    dispatch!(is_canonical, bool);
+    dispatch!(canonicalize, ILeb128Owned);
+
     dispatch!(expect_i8, i8);
+    dispatch!(try_decode_i8, Result<i8, Leb128Error>);
     dispatch!(expect_i16, i16);
+    dispatch!(try_decode_i16, Result<i16, Leb128Error>);
     dispatch!(expect_i32, i32);
+    dispatch!(try_decode_i32, Result<i32, Leb128Error>);
     dispatch!(expect_i64, i64);
-    dispatch!(expect_i128, [u8; 16]);
+    dispatch!(try_decode_i64, Result<i64, Leb128Error>);
+    dispatch!(expect_i128, i128);
+    dispatch!(try_decode_i128, Result<i128, Leb128Error>);
     dispatch!(expect_isize, isize);
+    dispatch!(try_decode_isize, Result<isize, Leb128Error>);
     dispatch!(decode_bytes, Vec<u8>);
 }
 
+#[cfg(feature = "alloc")]
 impl ULeb128Owned {
-    pub fn from_bytes(bytes: &[u8]) -> ULeb128Owned {
-        ULeb128::from_bytes(bytes).to_owned()
+    owned_impl!(ULeb128, ULeb128Owned);
+
+    /// Encode `value` using exactly `width` bytes, so that the slot can be
+    /// overwritten later with `ULeb128::patch_in_place`. Returns an error if
+    /// `value` does not fit in `width` bytes, or `width` is more than
+    /// `T::MAX_BYTES`.
+    pub fn encode_padded<T: EncodeULeb128>(value: T, width: usize) -> Result<ULeb128Owned, EncodeError> {
+        let mut bytes = Storage::zeroed(width);
+        ULeb128::patch_in_place(&mut bytes, value)?;
+        Ok(ULeb128Owned(bytes))
+    }
+
+    /// Encode `value`, which must fit in an unsigned integer of `N` bits.
+    pub fn encode_bits<const N: u32>(value: u128) -> Result<ULeb128Owned, EncodeError> {
+        let mut bytes = Storage::zeroed(uleb128_len(value));
+        ULeb128::encode_bits_to::<N>(value, &mut bytes)?;
+        Ok(ULeb128Owned(bytes))
     }
 
-    pub fn all_from_bytes(bytes: &[u8]) -> Vec<ULeb128Owned> {
-        ULeb128::all_from_bytes(bytes).into_iter().map(|i| i.to_owned()).collect()
+    pub fn decode_bits<const N: u32>(&self) -> u128 {
+        self.as_leb128().decode_bits::<N>()
     }
 
-    pub fn byte_count(self) -> usize {
-        self.0.len()
+    pub fn try_decode_bits<const N: u32>(&self) -> Result<u128, Leb128Error> {
+        self.as_leb128().try_decode_bits::<N>()
     }
 
-    pub fn as_ref(&self) -> ULeb128 {
-        ULeb128(&self.0)
+    /// Encode the `N` bit pattern of `value` as an unsigned integer of `N`
+    /// bits.
+    pub fn encode_signed_bits<const N: u32>(value: i128) -> Result<ULeb128Owned, EncodeError> {
+        let mut bytes = [0; MAX_INT_BYTES];
+        let count = ULeb128::encode_signed_bits_to::<N>(value, &mut bytes)?;
+        Ok(ULeb128Owned(Storage::from_slice(&bytes[..count])))
     }
 
+    pub fn try_decode_signed_bits<const N: u32>(&self) -> Result<i128, Leb128Error> {
+        self.as_leb128().try_decode_signed_bits::<N>()
+    }
+
+    /// Encode a signed integer as ZigZag-mapped unsigned LEB128.
+    pub fn encode_zigzag<T: ZigZag>(value: T) -> ULeb128Owned {
+        let mut bytes = Storage::zeroed(value.zigzag_len());
+        value.encode_zigzag_to(&mut bytes);
+        ULeb128Owned(bytes)
+    }
+
+    pub fn decode_zigzag<T: ZigZag>(&self) -> T {
+        self.as_leb128().decode_zigzag()
+    }
+
+    pub fn try_decode_zigzag<T: ZigZag>(&self) -> Result<T, Leb128Error> {
+        self.as_leb128().try_decode_zigzag()
+    }
+
+    dispatch!(is_canonical, bool);
+    dispatch!(canonicalize, ULeb128Owned);
+
     dispatch!(expect_u8, u8);
+    dispatch!(try_decode_u8, Result<u8, Leb128Error>);
     dispatch!(expect_u16, u16);
+    dispatch!(try_decode_u16, Result<u16, Leb128Error>);
     dispatch!(expect_u32, u32);
+    dispatch!(try_decode_u32, Result<u32, Leb128Error>);
     dispatch!(expect_u64, u64);
-    dispatch!(expect_u128, [u8; 16]);
+    dispatch!(try_decode_u64, Result<u64, Leb128Error>);
+    dispatch!(expect_u128, u128);
+    dispatch!(try_decode_u128, Result<u128, Leb128Error>);
     dispatch!(expect_usize, usize);
+    dispatch!(try_decode_usize, Result<usize, Leb128Error>);
     dispatch!(decode_bytes, Vec<u8>);
 }
 
-macro_rules! decode_signed {
-    ($name: ident, $t: ty) => {
+macro_rules! decode_as {
+    ($name: ident, $try_name: ident, $t: ty) => {
+        /// Panics if the number does not fit in the target type.
         pub fn $name(self) -> $t {
-            let mut result = 0;
-            let mut shift = 0;
-            let bit_count = mem::size_of::<$t>() * 8;
-            for &byte in self.0 {
-                result |= (byte & 0b0111_1111) as $t << shift;
-                shift += 7;
-                if byte & 0b1000_0000 == 0 {
-                    break;
-                }
-            }
-
-            let last_byte = self.0[self.0.len() - 1];
-            unsafe {
-                // I.e., if the last byte is positive
-                let size = if (last_byte & 0b0100_0000) == 0 {
-                    shift + 1 - std::intrinsics::ctlz(last_byte) as usize
-                } else {
-                    // Count the leading ones up to the first significant one.
-                    shift + 2 - std::intrinsics::ctlz(!(last_byte | 0b1000_0000)) as usize
-                };
-                assert!(size <= mem::size_of::<$t>() * 8,
-                        "overflow, expected {} byte(s)", mem::size_of::<$t>());
-            }
+            self.decode::<$t>()
+        }
 
-            // Sign extend if necessary.
-            if shift < bit_count && (last_byte & 0b0100_0000) != 0 {
-                result |= ((1 << shift) as $t).wrapping_neg();
-            }
-            result
-        }        
+        pub fn $try_name(self) -> Result<$t, Leb128Error> {
+            self.try_decode::<$t>()
+        }
     }
 }
 
 macro_rules! leb_ref_impl {
-    ($t: ident, $owned_t: ident) => {
+    ($t: ident, $owned_t: ident, $from: ident, $signed: expr) => {
         /// Read a single valid LEB128 number from bytes.
         /// Panics if there is not a valid LEB128 number in bytes.
         pub fn from_bytes(bytes: &'a [u8]) -> $t<'a> {
+            match $t::try_from_bytes(bytes) {
+                Ok(result) => result,
+                Err(_) => panic!("from_bytes on invalid input"),
+            }
+        }
+
+        /// Read a single valid LEB128 number from bytes. Any bytes after the
+        /// end of the number are ignored.
+        ///
+        /// The length of the number is not limited, so untrusted input may be
+        /// scanned to its end. Use `try_from_bytes_with` to limit it, or
+        /// `try_decode` the result, which checks the target type's limit.
+        pub fn try_from_bytes(bytes: &'a [u8]) -> Result<$t<'a>, Leb128Error> {
             let mut count = 0;
             for byte in bytes {
                 count += 1;
                 if byte & 0b1000_0000 == 0 {
-                    return $t(&bytes[0..count]);
+                    return Ok($t(&bytes[0..count]));
                 }
             }
-            panic!("from_bytes on invalid input");
+            Err(Leb128Error::Truncated { offset: bytes.len() })
+        }
+
+        /// Read a single valid LEB128 number from bytes, giving up after the
+        /// maximum length set in `options`, if any.
+        pub fn try_from_bytes_with(bytes: &'a [u8], options: DecodeOptions) -> Result<$t<'a>, Leb128Error> {
+            match $t::iter(bytes).with_options(options.max_count(1)).next() {
+                Some(result) => result,
+                None => Err(Leb128Error::Truncated { offset: 0 }),
+            }
+        }
+
+        /// Read a single valid LEB128 number from bytes, returning it and the
+        /// bytes after it.
+        pub fn split_first(bytes: &'a [u8]) -> Result<($t<'a>, &'a [u8]), Leb128Error> {
+            let number = $t::try_from_bytes(bytes)?;
+            Ok((number, &bytes[number.0.len()..]))
         }
 
         /// Read all of bytes into a Vec of LEB128 numbers. Panics if there
         /// are trailing bytes which are not part of a valid LEB128 number.
+        #[cfg(feature = "alloc")]
         pub fn all_from_bytes(bytes: &'a [u8]) -> Vec<$t<'a>> {
-            let mut result = vec![];
-            let mut start = 0;
-            let mut end = 0;
-            for byte in bytes {
-                end += 1;
-                if byte & 0b1000_0000 == 0 {
-                    result.push($t(&bytes[start..end]));
-                    start = end;
-                }
+            match $t::try_all_from_bytes(bytes) {
+                Ok(result) => result,
+                Err(_) => panic!("all_from_bytes on invalid input"),
             }
-            assert!(start == end, "all_from_bytes on invalid input");
-            
-            result
+        }
+
+        /// Read all of bytes into a Vec of LEB128 numbers. Returns an error
+        /// if there are trailing bytes which are not part of a valid LEB128
+        /// number.
+        ///
+        /// Neither the length nor the number of numbers is limited, so the Vec
+        /// may have an entry for every byte of untrusted input. Use
+        /// `try_all_from_bytes_with` to limit them.
+        #[cfg(feature = "alloc")]
+        pub fn try_all_from_bytes(bytes: &'a [u8]) -> Result<Vec<$t<'a>>, Leb128Error> {
+            $t::iter(bytes).collect()
+        }
+
+        /// Read all of bytes into a Vec of LEB128 numbers, within the limits
+        /// set in `options`.
+        #[cfg(feature = "alloc")]
+        pub fn try_all_from_bytes_with(bytes: &'a [u8], options: DecodeOptions) -> Result<Vec<$t<'a>>, Leb128Error> {
+            $t::iter(bytes).with_options(options).collect()
+        }
+
+        /// Iterate over the LEB128 numbers in bytes without allocating.
+        pub fn iter(bytes: &'a [u8]) -> Leb128Iter<'a, $t<'a>> {
+            Leb128Iter::new(bytes, $t)
         }
 
         pub fn byte_count(self) -> usize {
             self.0.len()
         }
 
-        pub fn to_owned(self) -> $owned_t {
-            $owned_t(self.0.to_owned())
+        /// Decode to any integer type, e.g., `number.decode::<u32>()`.
+        /// Panics if the number does not fit in the target type.
+        pub fn decode<T: FromLeb128>(self) -> T {
+            match self.try_decode() {
+                Ok(result) => result,
+                Err(e) => panic!("{}", e),
+            }
+        }
+
+        /// Decode to any integer type, e.g., `number.try_decode::<u32>()`.
+        pub fn try_decode<T: FromLeb128>(self) -> Result<T, Leb128Error> {
+            T::$from(self)
+        }
+
+        /// Decode to any integer type, using `options` to decide how to
+        /// handle numbers which do not fit.
+        pub fn try_decode_with<T: FromLeb128>(self, options: DecodeOptions) -> Result<T, Leb128Error> {
+            self.try_decode_reporting(options).map(|(value, _)| value)
+        }
+
+        /// As `try_decode_with`, also returning whether the number did not
+        /// fit, i.e., whether the value was saturated or wrapped.
+        pub fn try_decode_reporting<T: FromLeb128>(self, options: DecodeOptions) -> Result<(T, bool), Leb128Error> {
+            decode::decode_with(self.0, $signed, options)
+        }
+
+        /// Copy the bytes into an owned number.
+        #[cfg(feature = "alloc")]
+        pub fn into_owned(self) -> $owned_t {
+            $owned_t(Storage::from_slice(self.0))
+        }
+
+        /// Read a single valid LEB128 number from bytes, returning an error
+        /// if it is not encoded using the minimum number of bytes.
+        pub fn try_from_bytes_strict(bytes: &'a [u8]) -> Result<$t<'a>, Leb128Error> {
+            let result = $t::try_from_bytes(bytes)?;
+            let len = result.canonical_len();
+            if len < result.0.len() {
+                return Err(Leb128Error::NonCanonical { offset: len });
+            }
+            Ok(result)
+        }
+
+        /// Whether the number is encoded using the minimum number of bytes.
+        pub fn is_canonical(self) -> bool {
+            self.canonical_len() == self.0.len()
+        }
+
+        /// The same number, encoded using the minimum number of bytes.
+        #[cfg(feature = "alloc")]
+        pub fn canonicalize(self) -> $owned_t {
+            let mut bytes = Storage::from_slice(&self.0[..self.canonical_len()]);
+            let last = bytes.len() - 1;
+            bytes[last] &= 0b0111_1111;
+            $owned_t(bytes)
         }
     }
 }
 
 impl<'a> ILeb128<'a> {
-    leb_ref_impl!(ILeb128, ILeb128Owned);
+    leb_ref_impl!(ILeb128, ILeb128Owned, try_from_ileb128, true);
+
+    /// Overwrite `slot` with `value`, padded to exactly the length of the
+    /// slot. Returns an error if the slot is too short for `value`, or longer
+    /// than `T::MAX_BYTES`.
+    pub fn patch_in_place<T: EncodeILeb128>(slot: &mut [u8], value: T) -> Result<(), EncodeError> {
+        if slot.len() > T::MAX_BYTES {
+            return Err(EncodeError::SlotTooLong { max_bytes: T::MAX_BYTES });
+        }
+        let count = value.try_encode_ileb128_to(slot)?;
+        let fill = if slot[count - 1] & 0b0100_0000 != 0 { 0b0111_1111 } else { 0 };
+        pad(slot, count, fill);
+        Ok(())
+    }
 
-    decode_signed!(expect_i8, i8);
-    decode_signed!(expect_i16, i16);
-    decode_signed!(expect_i32, i32);
-    decode_signed!(expect_i64, i64);
-    decode_signed!(expect_isize, isize);
+    // The number of bytes in the minimal encoding of this number. A high byte
+    // is redundant if it only extends the sign of the byte below it.
+    fn canonical_len(self) -> usize {
+        let negative = self.0[self.0.len() - 1] & 0b0100_0000 != 0;
+        let fill = if negative { 0b0111_1111 } else { 0 };
+        let mut len = self.0.len();
+        while len > 1 &&
+              self.0[len - 1] & 0b0111_1111 == fill &&
+              (self.0[len - 2] & 0b0100_0000 != 0) == negative {
+            len -= 1;
+        }
+        len
+    }
 
-    // Returns the bytes in little-endian order, since Rust doesn't have a u128
-    // type.
-    pub fn expect_i128(self) -> [u8; 16] {
-        unimplemented!();
+    /// Decode a signed integer of `N` bits, where `N` is from 1 to 128,
+    /// e.g., `try_decode_bits::<33>()` for WebAssembly's `s33`.
+    pub fn try_decode_bits<const N: u32>(self) -> Result<i128, Leb128Error> {
+        const { assert!(N >= 1 && N <= 128, "bit width must be from 1 to 128") };
+        decode::decode_bits(self.0, true, N).map(|bits| bits as i128)
     }
 
-    // Prefer expect_* since they don't need to do any heap allocation.
-    pub fn decode_bytes(self) -> Vec<u8> {
-        unimplemented!();
+    /// Panics if the number does not fit in a signed integer of `N` bits.
+    pub fn decode_bits<const N: u32>(self) -> i128 {
+        match self.try_decode_bits::<N>() {
+            Ok(result) => result,
+            Err(e) => panic!("{}", e),
+        }
     }
-}
 
-macro_rules! decode_unsigned {
-    ($name: ident, $t: ty) => {
-        pub fn $name(self) -> $t {
-            let mut result = 0;
-            let mut shift = 0;
-            for &byte in self.0 {
-                result |= (byte & 0b0111_1111) as $t << shift;
-                shift += 7;
-                if byte & 0b1000_0000 == 0 {
-                    break;
-                }
-            }
+    /// Encode `value` into the start of `buf` and return the number of bytes
+    /// written, or an error if `value` does not fit in a signed integer of
+    /// `N` bits or `buf` is too short.
+    pub fn encode_bits_to<const N: u32>(value: i128, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        const { assert!(N >= 1 && N <= 128, "bit width must be from 1 to 128") };
+        let high = value >> (N - 1);
+        if high != 0 && high != -1 {
+            return Err(EncodeError::OutOfRange { bits: N });
+        }
+        value.try_encode_ileb128_to(buf)
+    }
 
-            unsafe {
-                let size = shift + 1 - std::intrinsics::ctlz(self.0[self.0.len() - 1]) as usize;
-                assert!(size <= mem::size_of::<$t>() * 8,
-                        "overflow, expected {} byte(s)", mem::size_of::<$t>());
+    /// Encode the `N` bit pattern `value` as a signed integer of `N` bits,
+    /// e.g., a `u32` which holds the bits of a WebAssembly `i32.const`.
+    /// Returns an error if `value` does not fit in `N` bits or `buf` is too
+    /// short.
+    pub fn encode_unsigned_bits_to<const N: u32>(value: u128, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        const { assert!(N >= 1 && N <= 128, "bit width must be from 1 to 128") };
+        if N < 128 && value >> N != 0 {
+            return Err(EncodeError::OutOfRange { bits: N });
+        }
+        ILeb128::encode_bits_to::<N>(sign_extend(value, N), buf)
+    }
+
+    /// Decode a signed integer of `N` bits to its `N` bit pattern, i.e., the
+    /// inverse of `encode_unsigned_bits_to`.
+    pub fn try_decode_unsigned_bits<const N: u32>(self) -> Result<u128, Leb128Error> {
+        self.try_decode_bits::<N>().map(|value| value as u128 & mask(N))
+    }
+
+    decode_as!(expect_i8, try_decode_i8, i8);
+    decode_as!(expect_i16, try_decode_i16, i16);
+    decode_as!(expect_i32, try_decode_i32, i32);
+    decode_as!(expect_i64, try_decode_i64, i64);
+    decode_as!(expect_i128, try_decode_i128, i128);
+    decode_as!(expect_isize, try_decode_isize, isize);
+
+    /// Decode a number of any size into little-endian two's complement
+    /// bytes, using the fewest bytes which can represent the number.
+    // Prefer expect_* since they don't need to do any heap allocation.
+    #[cfg(feature = "alloc")]
+    pub fn decode_bytes(self) -> Vec<u8> {
+        let last_byte = self.0[self.0.len() - 1];
+        let mut result = unpack(self.0, last_byte & 0b0100_0000 != 0);
+        while result.len() > 1 {
+            let (last, next) = (result[result.len() - 1], result[result.len() - 2]);
+            if (last == 0 && next & 0b1000_0000 == 0) ||
+               (last == 0xff && next & 0b1000_0000 != 0) {
+                result.pop();
+            } else {
+                break;
             }
-            result
-        }        
+        }
+        result
     }
 }
 
 impl<'a> ULeb128<'a> {
-    leb_ref_impl!(ULeb128, ULeb128Owned);
+    leb_ref_impl!(ULeb128, ULeb128Owned, try_from_uleb128, false);
+
+    /// Overwrite `slot` with `value`, padded to exactly the length of the
+    /// slot. Returns an error if the slot is too short for `value`, or longer
+    /// than `T::MAX_BYTES`.
+    pub fn patch_in_place<T: EncodeULeb128>(slot: &mut [u8], value: T) -> Result<(), EncodeError> {
+        if slot.len() > T::MAX_BYTES {
+            return Err(EncodeError::SlotTooLong { max_bytes: T::MAX_BYTES });
+        }
+        let count = value.try_encode_uleb128_to(slot)?;
+        pad(slot, count, 0);
+        Ok(())
+    }
+
+    // The number of bytes in the minimal encoding of this number. A high byte
+    // is redundant if it is zero.
+    fn canonical_len(self) -> usize {
+        let mut len = self.0.len();
+        while len > 1 && self.0[len - 1] & 0b0111_1111 == 0 {
+            len -= 1;
+        }
+        len
+    }
+
+    /// Decode an unsigned integer of `N` bits, where `N` is from 1 to 128,
+    /// e.g., `try_decode_bits::<1>()` for WebAssembly's `varuint1`.
+    pub fn try_decode_bits<const N: u32>(self) -> Result<u128, Leb128Error> {
+        const { assert!(N >= 1 && N <= 128, "bit width must be from 1 to 128") };
+        decode::decode_bits(self.0, false, N)
+    }
+
+    /// Panics if the number does not fit in an unsigned integer of `N` bits.
+    pub fn decode_bits<const N: u32>(self) -> u128 {
+        match self.try_decode_bits::<N>() {
+            Ok(result) => result,
+            Err(e) => panic!("{}", e),
+        }
+    }
 
-    decode_unsigned!(expect_u8, u8);
-    decode_unsigned!(expect_u16, u16);
-    decode_unsigned!(expect_u32, u32);
-    decode_unsigned!(expect_u64, u64);
-    decode_unsigned!(expect_usize, usize);
+    /// Encode `value` into the start of `buf` and return the number of bytes
+    /// written, or an error if `value` does not fit in an unsigned integer of
+    /// `N` bits or `buf` is too short.
+    pub fn encode_bits_to<const N: u32>(value: u128, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        const { assert!(N >= 1 && N <= 128, "bit width must be from 1 to 128") };
+        if N < 128 && value >> N != 0 {
+            return Err(EncodeError::OutOfRange { bits: N });
+        }
+        value.try_encode_uleb128_to(buf)
+    }
+
+    /// Encode the `N` bit pattern of `value`, which must fit in a signed
+    /// integer of `N` bits, as an unsigned integer of `N` bits. Returns an
+    /// error if `value` does not fit or `buf` is too short.
+    pub fn encode_signed_bits_to<const N: u32>(value: i128, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        const { assert!(N >= 1 && N <= 128, "bit width must be from 1 to 128") };
+        let high = value >> (N - 1);
+        if high != 0 && high != -1 {
+            return Err(EncodeError::OutOfRange { bits: N });
+        }
+        ULeb128::encode_bits_to::<N>(value as u128 & mask(N), buf)
+    }
+
+    /// Decode an unsigned integer of `N` bits and interpret its bits as a
+    /// signed integer of `N` bits, i.e., the inverse of
+    /// `encode_signed_bits_to`.
+    pub fn try_decode_signed_bits<const N: u32>(self) -> Result<i128, Leb128Error> {
+        self.try_decode_bits::<N>().map(|value| sign_extend(value, N))
+    }
+
+    /// Decode a ZigZag-mapped signed integer, e.g.,
+    /// `number.try_decode_zigzag::<i32>()` for Protocol Buffers' `sint32`.
+    pub fn try_decode_zigzag<T: ZigZag>(self) -> Result<T, Leb128Error> {
+        self.try_decode::<T::Unsigned>().map(T::from_zigzag)
+    }
 
-    // Returns the bytes in little-endian order, since Rust doesn't have a u128
-    // type.
-    pub fn expect_u128(self) -> [u8; 16] {
-        unimplemented!();
+    /// Panics if the number does not fit in the unsigned type of the same
+    /// width as `T`.
+    pub fn decode_zigzag<T: ZigZag>(self) -> T {
+        match self.try_decode_zigzag() {
+            Ok(result) => result,
+            Err(e) => panic!("{}", e),
+        }
     }
 
+    decode_as!(expect_u8, try_decode_u8, u8);
+    decode_as!(expect_u16, try_decode_u16, u16);
+    decode_as!(expect_u32, try_decode_u32, u32);
+    decode_as!(expect_u64, try_decode_u64, u64);
+    decode_as!(expect_u128, try_decode_u128, u128);
+    decode_as!(expect_usize, try_decode_usize, usize);
+
+    /// Decode a number of any size into little-endian bytes, using the
+    /// fewest bytes which can represent the number.
     // Prefer expect_* since they don't need to do any heap allocation.
+    #[cfg(feature = "alloc")]
     pub fn decode_bytes(self) -> Vec<u8> {
-        unimplemented!();
+        let mut result = unpack(self.0, false);
+        while result.len() > 1 && result[result.len() - 1] == 0 {
+            result.pop();
+        }
+        result
     }
 }
 
+// Repack the 7-bit groups of a LEB128 number into little-endian bytes. If
+// `negative`, the bits above the last group are set.
+#[cfg(feature = "alloc")]
+fn unpack(bytes: &[u8], negative: bool) -> Vec<u8> {
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut acc: u16 = 0;
+    let mut bits = 0;
+    for &byte in bytes {
+        acc |= ((byte & 0b0111_1111) as u16) << bits;
+        bits += 7;
+        if bits >= 8 {
+            result.push(acc as u8);
+            acc >>= 8;
+            bits -= 8;
+        }
+    }
+    if bits > 0 {
+        if negative {
+            acc |= 0xff << bits;
+        }
+        result.push(acc as u8);
+    }
+    result
+}
+
+// The low `bits` bits set.
+fn mask(bits: u32) -> u128 {
+    !0 >> (128 - bits)
+}
+
+// Interpret the low `bits` bits of `value` as a two's complement integer.
+fn sign_extend(value: u128, bits: u32) -> i128 {
+    ((value << (128 - bits)) as i128) >> (128 - bits)
+}
+
+// Copy the used bytes of an encoded array to the start of `buf`, leaving `buf`
+// unchanged if it is too short.
+fn try_copy_to<A: AsRef<[u8]>>((bytes, count): (A, usize), buf: &mut [u8]) -> Result<usize, EncodeError> {
+    if buf.len() < count {
+        return Err(EncodeError::BufferTooSmall { needed: count });
+    }
+    buf[..count].copy_from_slice(&bytes.as_ref()[..count]);
+    Ok(count)
+}
 
+// Extend the `count` byte LEB128 number at the start of `buf` to fill all of
+// `buf`, using continuation bytes with `fill` as their payload.
+fn pad(buf: &mut [u8], count: usize, fill: u8) {
+    let width = buf.len();
+    if count == width {
+        return;
+    }
+    buf[count - 1] |= 0b1000_0000;
+    for byte in &mut buf[count..width - 1] {
+        *byte = fill | 0b1000_0000;
+    }
+    buf[width - 1] = fill;
+}
+
+// Pack little-endian bytes into enough 7-bit groups to hold `bit_count` bits
+// (and at least one group). Bytes past the end of `bytes` are taken to be
+// `fill`.
+#[cfg(feature = "alloc")]
+fn pack(bytes: &[u8], bit_count: usize, fill: u8) -> Vec<u8> {
+    let group_count = core::cmp::max(1, bit_count.div_ceil(7));
+    let mut result = Vec::with_capacity(group_count);
+    let mut bytes = bytes.iter();
+    let mut acc: u16 = 0;
+    let mut bits = 0;
+    for i in 0..group_count {
+        if bits < 7 {
+            acc |= (*bytes.next().unwrap_or(&fill) as u16) << bits;
+            bits += 8;
+        }
+        let mut byte = acc as u8 & 0b0111_1111;
+        acc >>= 7;
+        bits -= 7;
+        if i + 1 < group_count {
+            byte |= 0b1000_0000;
+        }
+        result.push(byte);
+    }
+    result
+}
+
+
+#[cfg(feature = "alloc")]
 pub trait ToILeb128Owned: Sized {
     fn encode(self) -> ILeb128Owned;
 }
 
+#[cfg(feature = "alloc")]
 pub trait ToULeb128Owned: Sized {
     fn encode(self) -> ULeb128Owned;
 }
 
+// The length of the longest encoding of any integer type (a u128 or i128).
+#[cfg(feature = "alloc")]
+const MAX_INT_BYTES: usize = 19;
+
+/// Integer types which can be encoded as signed LEB128 without allocating. The trait
+/// is sealed, see `FromLeb128`.
+pub trait EncodeILeb128: Copy + sealed::Sealed {
+    /// The length of the longest encoding of a value of this type.
+    const MAX_BYTES: usize;
+
+    /// A byte array of length `MAX_BYTES`.
+    type Array: AsRef<[u8]> + AsMut<[u8]> + Copy;
+
+    /// Encode into the start of `buf` and return the number of bytes written.
+    /// Panics if `buf` is too short; `MAX_BYTES` is always long enough.
+    fn encode_ileb128_to(self, buf: &mut [u8]) -> usize;
+
+    /// Encode into a stack-allocated array. Returns the array and the number
+    /// of bytes of it which are used.
+    fn encode_ileb128_array(self) -> (Self::Array, usize);
+
+    /// Encode into the start of `buf` and return the number of bytes written,
+    /// or an error if `buf` is too short. `buf` is not modified on error.
+    fn try_encode_ileb128_to(self, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        try_copy_to(self.encode_ileb128_array(), buf)
+    }
+}
+
+/// Integer types which can be encoded as unsigned LEB128 without allocating. The trait
+/// is sealed, see `FromLeb128`.
+pub trait EncodeULeb128: Copy + sealed::Sealed {
+    /// The length of the longest encoding of a value of this type.
+    const MAX_BYTES: usize;
+
+    /// A byte array of length `MAX_BYTES`.
+    type Array: AsRef<[u8]> + AsMut<[u8]> + Copy;
+
+    /// Encode into the start of `buf` and return the number of bytes written.
+    /// Panics if `buf` is too short; `MAX_BYTES` is always long enough.
+    fn encode_uleb128_to(self, buf: &mut [u8]) -> usize;
+
+    /// Encode into a stack-allocated array. Returns the array and the number
+    /// of bytes of it which are used.
+    fn encode_uleb128_array(self) -> (Self::Array, usize);
+
+    /// Encode into the start of `buf` and return the number of bytes written,
+    /// or an error if `buf` is too short. `buf` is not modified on error.
+    fn try_encode_uleb128_to(self, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        try_copy_to(self.encode_uleb128_array(), buf)
+    }
+}
+
+/// Integer types which can be encoded as LEB128 without allocating, using
+/// signed LEB128 for signed types and unsigned LEB128 for unsigned types. The
+/// trait is sealed, see `FromLeb128`.
+pub trait ToLeb128: Copy + sealed::Sealed {
+    /// Whether the type is encoded as signed LEB128.
+    const SIGNED: bool;
+
+    /// The length of the longest encoding of a value of this type.
+    const MAX_BYTES: usize;
+
+    /// A byte array of length `MAX_BYTES`.
+    type Array: AsRef<[u8]> + AsMut<[u8]> + Copy;
+
+    /// Encode into the start of `buf` and return the number of bytes written.
+    /// Panics if `buf` is too short; `MAX_BYTES` is always long enough.
+    fn encode_leb128_to(self, buf: &mut [u8]) -> usize;
+
+    /// Encode into a stack-allocated array. Returns the array and the number
+    /// of bytes of it which are used.
+    fn encode_leb128_array(self) -> (Self::Array, usize);
+
+    /// The number of bytes in the encoding of this value.
+    fn leb128_len(self) -> usize;
+
+    /// Encode into the start of `buf` and return the number of bytes written,
+    /// or an error if `buf` is too short. `buf` is not modified on error.
+    fn try_encode_leb128_to(self, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        try_copy_to(self.encode_leb128_array(), buf)
+    }
+}
+
+// Implement `ToLeb128` by forwarding to `EncodeILeb128` or `EncodeULeb128`.
+macro_rules! impl_to_leb128 {
+    ($t: ident, $signed: expr, $trait_: ident, $encode_to: ident, $encode_array: ident, $len: ident, $wide: ty) => {
+        impl ToLeb128 for $t {
+            const SIGNED: bool = $signed;
+            const MAX_BYTES: usize = <$t as $trait_>::MAX_BYTES;
+            type Array = <$t as $trait_>::Array;
+
+            fn encode_leb128_to(self, buf: &mut [u8]) -> usize {
+                self.$encode_to(buf)
+            }
+
+            fn encode_leb128_array(self) -> (Self::Array, usize) {
+                self.$encode_array()
+            }
+
+            fn leb128_len(self) -> usize {
+                $len(self as $wide)
+            }
+        }
+    }
+}
+
 macro_rules! impl_encode_signed {
     ($t: ident) => {
-        impl ToILeb128Owned for $t {
-            fn encode(mut self) -> ILeb128Owned {
+        impl EncodeILeb128 for $t {
+            const MAX_BYTES: usize = (mem::size_of::<$t>() * 8).div_ceil(7);
+            type Array = [u8; (mem::size_of::<$t>() * 8).div_ceil(7)];
+
+            fn encode_ileb128_array(self) -> (Self::Array, usize) {
+                let mut buf = [0; (mem::size_of::<$t>() * 8).div_ceil(7)];
+                let count = self.encode_ileb128_to(&mut buf);
+                (buf, count)
+            }
+
+            fn encode_ileb128_to(mut self, buf: &mut [u8]) -> usize {
                 const SIGN_BIT: u8 = 0b0100_0000;
-                let mut result = vec![];
+                let mut count = 0;
                 let mut more = true;
                 loop {
                     let mut byte = self as u8 & 0b0111_1111;
@@ -258,36 +1112,69 @@ macro_rules! impl_encode_signed {
                     } else {
                         byte |= 0b1000_0000;
                     }
-                    result.push(byte);
+                    buf[count] = byte;
+                    count += 1;
 
                     if !more {
-                        return ILeb128Owned(result);
+                        return count;
                     }
                 }
             }
         }
+
+        #[cfg(feature = "alloc")]
+        impl ToILeb128Owned for $t {
+            fn encode(self) -> ILeb128Owned {
+                let mut bytes = Storage::zeroed(sleb128_len(self as i128));
+                self.encode_ileb128_to(&mut bytes);
+                ILeb128Owned(bytes)
+            }
+        }
+
+        impl_to_leb128!($t, true, EncodeILeb128, encode_ileb128_to, encode_ileb128_array, sleb128_len, i128);
     }
 }
 
 macro_rules! impl_encode_unsigned {
     ($t: ident) => {
-        impl ToULeb128Owned for $t {
-            fn encode(mut self) -> ULeb128Owned {
-                let mut result = vec![];
+        impl EncodeULeb128 for $t {
+            const MAX_BYTES: usize = (mem::size_of::<$t>() * 8).div_ceil(7);
+            type Array = [u8; (mem::size_of::<$t>() * 8).div_ceil(7)];
+
+            fn encode_uleb128_array(self) -> (Self::Array, usize) {
+                let mut buf = [0; (mem::size_of::<$t>() * 8).div_ceil(7)];
+                let count = self.encode_uleb128_to(&mut buf);
+                (buf, count)
+            }
+
+            fn encode_uleb128_to(mut self, buf: &mut [u8]) -> usize {
+                let mut count = 0;
                 loop {
                     let mut byte = self as u8 & 0b0111_1111;
                     self >>= 7;
                     if self != 0 {
                         byte |= 0b1000_0000;
                     }
-                    result.push(byte);
+                    buf[count] = byte;
+                    count += 1;
 
                     if self == 0 {
-                        return ULeb128Owned(result);
+                        return count;
                     }
                 }
             }
         }
+
+        #[cfg(feature = "alloc")]
+        impl ToULeb128Owned for $t {
+            fn encode(self) -> ULeb128Owned {
+                let mut bytes = Storage::zeroed(uleb128_len(self as u128));
+                self.encode_uleb128_to(&mut bytes);
+                ULeb128Owned(bytes)
+            }
+        }
+
+        impl_to_leb128!($t, false, EncodeULeb128, encode_uleb128_to, encode_uleb128_array, uleb128_len, u128);
     }
 }
 
@@ -295,28 +1182,50 @@ impl_encode_signed!(i8);
 impl_encode_signed!(i16);
 impl_encode_signed!(i32);
 impl_encode_signed!(i64);
+impl_encode_signed!(i128);
 impl_encode_signed!(isize);
 impl_encode_unsigned!(u8);
 impl_encode_unsigned!(u16);
 impl_encode_unsigned!(u32);
 impl_encode_unsigned!(u64);
+impl_encode_unsigned!(u128);
 impl_encode_unsigned!(usize);
 
-impl<'a> ToILeb128Owned for &'a [u8] {
+/// Encodes little-endian two's complement bytes of any length. An empty slice
+/// is encoded as zero.
+#[cfg(feature = "alloc")]
+impl ToILeb128Owned for &[u8] {
     fn encode(self) -> ILeb128Owned {
-        unimplemented!();
+        let fill = match self.last() {
+            Some(&byte) if byte & 0b1000_0000 != 0 => 0xff,
+            _ => 0,
+        };
+        // Count the significant bits, plus one for the sign.
+        let bit_count = match self.iter().rposition(|&byte| byte != fill) {
+            Some(i) => i * 8 + (8 - (self[i] ^ fill).leading_zeros() as usize) + 1,
+            None => 1,
+        };
+        ILeb128Owned(Storage::from_vec(pack(self, bit_count, fill)))
     }
 }
 
-impl<'a> ToULeb128Owned for &'a [u8] {
+/// Encodes little-endian bytes of any length. An empty slice is encoded as
+/// zero.
+#[cfg(feature = "alloc")]
+impl ToULeb128Owned for &[u8] {
     fn encode(self) -> ULeb128Owned {
-        unimplemented!();
+        let bit_count = match self.iter().rposition(|&byte| byte != 0) {
+            Some(i) => i * 8 + (8 - self[i].leading_zeros() as usize),
+            None => 0,
+        };
+        ULeb128Owned(Storage::from_vec(pack(self, bit_count, 0)))
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "alloc"))]
 mod test {
     use super::*;
+    use std::string::ToString;
 
     #[test]
     fn test_unsigned_encode() {
@@ -541,6 +1450,399 @@ mod test {
         ILeb128Owned::from_bytes(&[128, 128, 128, 128, 128, 128, 128, 128, 128, 2]).expect_i64();
     }
 
+    #[test]
+    fn test_128_bit() {
+        let mut max = vec![0xff; 18];
+        max.push(0b11);
+        assert!(u128::MAX.encode() == ULeb128Owned::from_bytes(&max));
+        assert!(ULeb128Owned::from_bytes(&max).expect_u128() == u128::MAX);
+        assert!((624485u128).encode() == ULeb128Owned::from_bytes(&[0xE5, 0x8E, 0x26]));
+        assert!(ULeb128Owned::from_bytes(&[0xE5, 0x8E, 0x26]).expect_u128() == 624485);
+
+        let mut min = vec![0x80; 18];
+        min.push(0x7e);
+        assert!(i128::MIN.encode() == ILeb128Owned::from_bytes(&min));
+        assert!(ILeb128Owned::from_bytes(&min).expect_i128() == i128::MIN);
+        let mut max = vec![0xff; 18];
+        max.push(0x01);
+        assert!(i128::MAX.encode() == ILeb128Owned::from_bytes(&max));
+        assert!(ILeb128Owned::from_bytes(&max).expect_i128() == i128::MAX);
+        assert!((-129i128).encode() == ILeb128Owned::from_bytes(&[0xff, 0x7e]));
+        assert!(ILeb128Owned::from_bytes(&[0xff, 0x7e]).expect_i128() == -129);
+        assert!(ILeb128Owned::from_bytes(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x7f]).expect_i128() ==
+                -(1 << 70));
+
+        for &v in &[0u128, 1, 127, 128, 1 << 64, (1 << 127) + 12345, u128::MAX] {
+            assert!(v.encode().expect_u128() == v);
+        }
+        for &v in &[0i128, -1, 63, -64, 64, -65, 1 << 100, -(1 << 100), i128::MIN, i128::MAX] {
+            assert!(v.encode().expect_i128() == v);
+        }
+    }
+
+    #[test]
+    fn test_decode_overflow_128() {
+        let mut bytes = vec![0x80; 18];
+        bytes.push(0x04);
+        assert!(ULeb128Owned::from_bytes(&bytes).try_decode_u128() ==
+                Err(Leb128Error::Overflow { target_bits: 128, offset: 18 }));
+        assert!(ILeb128Owned::from_bytes(&bytes).try_decode_i128() ==
+                Err(Leb128Error::Overflow { target_bits: 128, offset: 18 }));
+
+        let mut bytes = vec![0x80; 19];
+        bytes.push(0x00);
+        assert!(ULeb128Owned::from_bytes(&bytes).try_decode_u128() ==
+                Err(Leb128Error::TooLong { max_bytes: 19, offset: 19 }));
+        assert!(ILeb128Owned::from_bytes(&bytes).try_decode_i128() ==
+                Err(Leb128Error::TooLong { max_bytes: 19, offset: 19 }));
+    }
+
+    #[test]
+    fn test_decode_bytes() {
+        assert!(ULeb128Owned::from_bytes(&[0]).decode_bytes() == vec![0]);
+        assert!(ULeb128Owned::from_bytes(&[0x80, 0x80, 0]).decode_bytes() == vec![0]);
+        assert!(ULeb128Owned::from_bytes(&[128, 1]).decode_bytes() == vec![128]);
+        assert!(ULeb128Owned::from_bytes(&[0xE5, 0x8E, 0x26]).decode_bytes() == vec![0x65, 0x87, 0x09]);
+
+        assert!(ILeb128Owned::from_bytes(&[0]).decode_bytes() == vec![0]);
+        assert!(ILeb128Owned::from_bytes(&[0x7f]).decode_bytes() == vec![0xff]);
+        assert!(ILeb128Owned::from_bytes(&[0xff, 0x7f]).decode_bytes() == vec![0xff]);
+        assert!(ILeb128Owned::from_bytes(&[0xff, 0]).decode_bytes() == vec![127]);
+        assert!(ILeb128Owned::from_bytes(&[0x80, 1]).decode_bytes() == vec![128, 0]);
+        assert!(ILeb128Owned::from_bytes(&[0x80, 0x7f]).decode_bytes() == vec![0x80]);
+        assert!(ILeb128Owned::from_bytes(&[0xff, 0x7e]).decode_bytes() == vec![0x7f, 0xff]);
+    }
+
+    #[test]
+    fn test_encode_bytes() {
+        assert!(ToULeb128Owned::encode(&[][..]) == ULeb128Owned::from_bytes(&[0]));
+        assert!(ToULeb128Owned::encode(&[0, 0, 0][..]) == ULeb128Owned::from_bytes(&[0]));
+        assert!(ToULeb128Owned::encode(&[0x65, 0x87, 0x09, 0][..]) == ULeb128Owned::from_bytes(&[0xE5, 0x8E, 0x26]));
+
+        assert!(ToILeb128Owned::encode(&[][..]) == ILeb128Owned::from_bytes(&[0]));
+        assert!(ToILeb128Owned::encode(&[0xff, 0xff][..]) == ILeb128Owned::from_bytes(&[0x7f]));
+        assert!(ToILeb128Owned::encode(&[0x80, 0][..]) == ILeb128Owned::from_bytes(&[0x80, 1]));
+        assert!(ToILeb128Owned::encode(&[0x7f, 0xff][..]) == ILeb128Owned::from_bytes(&[0xff, 0x7e]));
+
+        for &v in &[0u64, 1, 127, 128, 255, 256, 624485, 1 << 63, u64::MAX] {
+            let bytes = v.to_le_bytes();
+            assert!(ToULeb128Owned::encode(&bytes[..]) == v.encode());
+            assert!(ToULeb128Owned::encode(&v.encode().decode_bytes()[..]) == v.encode());
+        }
+        for &v in &[0i64, 1, -1, 63, -64, 64, -65, 127, -128, -129, i64::MIN, i64::MAX] {
+            let bytes = v.to_le_bytes();
+            assert!(ToILeb128Owned::encode(&bytes[..]) == v.encode());
+            assert!(ToILeb128Owned::encode(&v.encode().decode_bytes()[..]) == v.encode());
+        }
+    }
+
+    #[test]
+    fn test_bytes_round_trip_wide() {
+        // 2^200 and -2^200 do not fit in any native integer type.
+        let mut bytes = vec![0; 25];
+        bytes.push(1);
+        let encoded = ToULeb128Owned::encode(&bytes[..]);
+        assert!(encoded.clone().byte_count() == 29);
+        assert!(encoded.decode_bytes() == bytes);
+        assert!(encoded.try_decode_u128().is_err());
+
+        let mut bytes = vec![0; 25];
+        bytes.push(0xff);
+        let encoded = ToILeb128Owned::encode(&bytes[..]);
+        assert!(encoded.decode_bytes() == bytes);
+        assert!(encoded.try_decode_i128().is_err());
+    }
+
+    #[test]
+    fn test_encode_to_slice() {
+        let mut buf = [0xaa; 4];
+        assert!(624485u32.try_encode_uleb128_to(&mut buf) == Ok(3));
+        assert!(buf == [0xE5, 0x8E, 0x26, 0xaa]);
+        assert!((-129i64).try_encode_ileb128_to(&mut buf[1..]) == Ok(2));
+        assert!(buf == [0xE5, 0xff, 0x7e, 0xaa]);
+
+        assert!(624485u64.try_encode_uleb128_to(&mut buf[..2]) == Err(EncodeError::BufferTooSmall { needed: 3 }));
+        assert!(i128::MIN.try_encode_ileb128_to(&mut buf) == Err(EncodeError::BufferTooSmall { needed: 19 }));
+        assert!(buf == [0xE5, 0xff, 0x7e, 0xaa]);
+        assert!(0u8.try_encode_uleb128_to(&mut []) == Err(EncodeError::BufferTooSmall { needed: 1 }));
+
+        assert!(255u8.encode_uleb128_to(&mut buf) == 2);
+        assert!(buf[..2] == [255, 1]);
+    }
+
+    #[test]
+    fn test_encode_array() {
+        let (bytes, count): ([u8; 10], usize) = u64::MAX.encode_uleb128_array();
+        assert!(count == 10);
+        assert!(ULeb128::from_bytes(&bytes[..count]).expect_u64() == u64::MAX);
+
+        let (bytes, count): ([u8; 19], usize) = (-2i128).encode_ileb128_array();
+        assert!(bytes[..count] == [0x7e]);
+
+        let (bytes, count): ([u8; 2], usize) = (-128i8).encode_ileb128_array();
+        assert!(bytes[..count] == [0x80, 0x7f]);
+    }
+
+    #[test]
+    fn test_generic_round_trip() {
+        fn round_trip<T: ToLeb128 + FromLeb128>(value: T) -> T {
+            let (bytes, count) = value.encode_leb128_array();
+            if <T as ToLeb128>::SIGNED {
+                ILeb128::from_bytes(&bytes.as_ref()[..count]).decode()
+            } else {
+                ULeb128::from_bytes(&bytes.as_ref()[..count]).decode()
+            }
+        }
+        assert!(round_trip(624485u32) == 624485);
+        assert!(round_trip(-129i16) == -129);
+        assert!(round_trip(u128::MAX) == u128::MAX);
+        assert!(round_trip(i64::MIN) == i64::MIN);
+
+        let mut buf = [0; 2];
+        assert!(300u16.try_encode_leb128_to(&mut buf) == Ok(2));
+        assert!(buf == [0xac, 2]);
+        assert!((-8193i32).try_encode_leb128_to(&mut buf) == Err(EncodeError::BufferTooSmall { needed: 3 }));
+        assert!(ULeb128Owned::from_bytes(&[0xac, 2]).decode::<u64>() == 300);
+    }
+
+    #[test]
+    fn test_canonical() {
+        assert!(ULeb128::from_bytes(&[0]).is_canonical());
+        assert!(ULeb128::from_bytes(&[128, 1]).is_canonical());
+        assert!(!ULeb128::from_bytes(&[0x80, 0]).is_canonical());
+        assert!(!ULeb128::from_bytes(&[0xE5, 0x8E, 0xA6, 0x80, 0]).is_canonical());
+
+        assert!(ILeb128::from_bytes(&[0x7f]).is_canonical());
+        assert!(ILeb128::from_bytes(&[0xff, 0]).is_canonical());
+        assert!(ILeb128::from_bytes(&[0x80, 0x7f]).is_canonical());
+        assert!(ILeb128::from_bytes(&[0xc0, 0]).is_canonical());
+        assert!(!ILeb128::from_bytes(&[0xff, 0x7f]).is_canonical());
+        assert!(!ILeb128::from_bytes(&[0x80, 0]).is_canonical());
+        assert!(!ILeb128::from_bytes(&[0xc0, 0xff, 0x7f]).is_canonical());
+        assert!(!ILeb128Owned::from_bytes(&[0x82, 0x80, 0]).is_canonical());
+    }
+
+    #[test]
+    fn test_from_bytes_strict() {
+        assert!(ULeb128::try_from_bytes_strict(&[128, 1, 5]).unwrap().byte_count() == 2);
+        assert!(ULeb128::try_from_bytes_strict(&[0x80, 0x80, 0]) ==
+                Err(Leb128Error::NonCanonical { offset: 1 }));
+        assert!(ULeb128::try_from_bytes_strict(&[0x80, 0x80]) ==
+                Err(Leb128Error::Truncated { offset: 2 }));
+        assert!(ILeb128Owned::try_from_bytes_strict(&[0xff, 0x7f]) ==
+                Err(Leb128Error::NonCanonical { offset: 1 }));
+        assert!(ILeb128Owned::try_from_bytes_strict(&[0xff, 0x7e]).is_ok());
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        assert!(ULeb128::from_bytes(&[0x80, 0x80, 0]).canonicalize() == ULeb128Owned::from_bytes(&[0]));
+        assert!(ULeb128::from_bytes(&[0xE5, 0x8E, 0xA6, 0x80, 0]).canonicalize() == 624485u32.encode());
+        assert!(ILeb128::from_bytes(&[0xff, 0xff, 0x7f]).canonicalize() == (-1i8).encode());
+        assert!(ILeb128::from_bytes(&[0xc0, 0xff, 0x7f]).canonicalize() == (-64i8).encode());
+        assert!(ILeb128Owned::from_bytes(&[0xc0, 0x80, 0]).canonicalize() == (64i8).encode());
+        assert!(ILeb128Owned::from_bytes(&[0xff, 0x7e]).canonicalize() == (-129i16).encode());
+    }
+
+    #[test]
+    fn test_encode_padded() {
+        assert!(ULeb128Owned::encode_padded(0u32, 5).unwrap() == ULeb128Owned::from_bytes(&[0x80, 0x80, 0x80, 0x80, 0]));
+        assert!(ULeb128Owned::encode_padded(624485u32, 5).unwrap() == ULeb128Owned::from_bytes(&[0xE5, 0x8E, 0xA6, 0x80, 0]));
+        assert!(ULeb128Owned::encode_padded(624485u32, 3).unwrap() == ULeb128Owned::from_bytes(&[0xE5, 0x8E, 0x26]));
+        assert!(ULeb128Owned::encode_padded(624485u32, 2) == Err(EncodeError::BufferTooSmall { needed: 3 }));
+        assert!(ULeb128Owned::encode_padded(0u8, 0) == Err(EncodeError::BufferTooSmall { needed: 1 }));
+        assert!(ULeb128Owned::encode_padded(u32::MAX, 5).unwrap().expect_u32() == u32::MAX);
+        assert!(ULeb128Owned::encode_padded(1u32, 6) == Err(EncodeError::SlotTooLong { max_bytes: 5 }));
+        assert!(ILeb128Owned::encode_padded(-1i8, 2).unwrap().expect_i8() == -1);
+        assert!(ILeb128Owned::encode_padded(-1i8, 3) == Err(EncodeError::SlotTooLong { max_bytes: 2 }));
+
+        assert!(ILeb128Owned::encode_padded(-1i32, 3).unwrap() == ILeb128Owned::from_bytes(&[0xff, 0xff, 0x7f]));
+        assert!(ILeb128Owned::encode_padded(-129i32, 5).unwrap() == ILeb128Owned::from_bytes(&[0xff, 0xfe, 0xff, 0xff, 0x7f]));
+        assert!(ILeb128Owned::encode_padded(64i32, 3).unwrap() == ILeb128Owned::from_bytes(&[0xc0, 0x80, 0]));
+        assert!(ILeb128Owned::encode_padded(-129i32, 1) == Err(EncodeError::BufferTooSmall { needed: 2 }));
+        for &v in &[0i32, 1, -1, 63, -64, 64, -65, i32::MIN, i32::MAX] {
+            let padded = ILeb128Owned::encode_padded(v, 5).unwrap();
+            assert!(padded.clone().byte_count() == 5);
+            assert!(padded.expect_i32() == v);
+        }
+    }
+
+    #[test]
+    fn test_patch_in_place() {
+        let mut bytes = [42, 0x80, 0x80, 0x80, 0x80, 0, 7];
+        ULeb128::patch_in_place(&mut bytes[1..6], 624485u32).unwrap();
+        assert!(bytes == [42, 0xE5, 0x8E, 0xA6, 0x80, 0, 7]);
+        ULeb128::patch_in_place(&mut bytes[1..6], 1u32).unwrap();
+        assert!(bytes == [42, 0x81, 0x80, 0x80, 0x80, 0, 7]);
+        assert!(ULeb128::patch_in_place(&mut bytes[1..6], 1u8) == Err(EncodeError::SlotTooLong { max_bytes: 2 }));
+        assert!(bytes == [42, 0x81, 0x80, 0x80, 0x80, 0, 7]);
+        assert!(ULeb128::patch_in_place(&mut bytes[1..3], 624485u32) == Err(EncodeError::BufferTooSmall { needed: 3 }));
+
+        ILeb128::patch_in_place(&mut bytes[1..6], -2i64).unwrap();
+        assert!(bytes == [42, 0xfe, 0xff, 0xff, 0xff, 0x7f, 7]);
+        assert!(ILeb128::from_bytes(&bytes[1..]).expect_i64() == -2);
+    }
+
+    #[test]
+    fn test_bytes_access() {
+        let owned = ULeb128Owned::from_bytes(&[0xE5, 0x8E, 0x26]);
+        assert!(*owned == [0xE5, 0x8E, 0x26]);
+        assert!(AsRef::<[u8]>::as_ref(&owned) == &[0xE5, 0x8E, 0x26][..]);
+        assert!(owned.len() == 3);
+
+        let borrowed = ILeb128::from_bytes(&[0xff, 0x7e, 42]);
+        assert!(*borrowed == [0xff, 0x7e]);
+        assert!(AsRef::<[u8]>::as_ref(&borrowed) == &[0xff, 0x7e][..]);
+        assert!(borrowed.last() == Some(&0x7e));
+    }
+
+    #[test]
+    fn test_from_conversions() {
+        let owned = ULeb128Owned::from(ULeb128::from_bytes(&[128, 1]));
+        assert!(owned == 128u32);
+        let borrowed = ULeb128::from(&owned);
+        assert!(borrowed == owned);
+
+        let owned: ILeb128Owned = ILeb128::from_bytes(&[0x7e]).into();
+        let borrowed: ILeb128 = (&owned).into();
+        assert!(borrowed.expect_i8() == -2);
+
+        // The std traits are not hidden by the conversion methods.
+        let owned = ULeb128::from_bytes(&[0x80, 1]).into_owned();
+        assert!(owned.as_leb128() == 128u32);
+        let bytes: &[u8] = owned.as_ref();
+        assert!(bytes == [0x80, 1]);
+        let borrowed = ILeb128::from_bytes(&[0x7e]);
+        let copy: ILeb128 = ::alloc::borrow::ToOwned::to_owned(&borrowed);
+        assert!(copy.into_owned() == -2i8);
+    }
+
+    #[test]
+    fn test_limits() {
+        let options = DecodeOptions::new().max_bytes(2);
+        assert!(ULeb128::try_from_bytes_with(&[0x80, 0x80, 0x80], options) ==
+                Err(Leb128Error::TooLong { max_bytes: 2, offset: 2 }));
+        assert!(ULeb128::try_from_bytes_with(&[0x80, 1, 0x80], options).unwrap() == 128u8);
+        assert!(ULeb128::try_from_bytes_with(&[], options) == Err(Leb128Error::Truncated { offset: 0 }));
+
+        // The default is the tight bound for the target type.
+        let padded = ULeb128::from_bytes(&[0x80, 0x80, 0]);
+        assert!(padded.try_decode_with::<u8>(DecodeOptions::new()) ==
+                Err(Leb128Error::TooLong { max_bytes: 2, offset: 2 }));
+        assert!(padded.try_decode_with::<u8>(DecodeOptions::new().max_bytes(3)) == Ok(0));
+
+        let options = DecodeOptions::new().max_count(3).max_total_bytes(4);
+        assert!(ILeb128Owned::try_all_from_bytes_with(&[1, 0x7f, 0xff, 0x7e], options).unwrap().len() == 3);
+        assert!(ILeb128Owned::try_all_from_bytes_with(&[1, 2, 3, 4], options) ==
+                Err(Leb128Error::TooMany { max_count: 3, offset: 3 }));
+        assert!(ILeb128::try_all_from_bytes_with(&[1, 0xff, 0xff, 0xff, 0x7f], options) ==
+                Err(Leb128Error::OverBudget { max_total_bytes: 4, offset: 4 }));
+        assert!(Leb128Error::OverBudget { max_total_bytes: 4, offset: 4 }.to_string() ==
+                "LEB128 numbers longer than 4 byte(s) in total at offset 4");
+    }
+
+    #[test]
+    fn test_bit_widths() {
+        // WebAssembly block types are s33, with negative values for types.
+        assert!(ILeb128::from_bytes(&[0x40]).try_decode_bits::<33>() == Ok(-64));
+        assert!(ILeb128::from_bytes(&[0xff, 0xff, 0xff, 0xff, 0x0f]).try_decode_bits::<33>() == Ok(u32::MAX as i128));
+        assert!(ILeb128::from_bytes(&[0xff, 0xff, 0xff, 0xff, 0x1f]).try_decode_bits::<33>() ==
+                Err(Leb128Error::Overflow { target_bits: 33, offset: 4 }));
+        assert!(ILeb128::from_bytes(&[0x80, 0x80, 0x80, 0x80, 0x70]).decode_bits::<33>() == -(1 << 32));
+
+        assert!(ULeb128::from_bytes(&[1]).try_decode_bits::<1>() == Ok(1));
+        assert!(ULeb128::from_bytes(&[2]).try_decode_bits::<1>() == Err(Leb128Error::Overflow { target_bits: 1, offset: 0 }));
+        assert!(ULeb128::from_bytes(&[0x81, 0]).try_decode_bits::<1>() == Err(Leb128Error::TooLong { max_bytes: 1, offset: 1 }));
+        assert!(ULeb128::from_bytes(&[0x7f]).decode_bits::<7>() == 127);
+        assert!(ILeb128::from_bytes(&[0x40]).try_decode_bits::<7>() == Ok(-64));
+        assert!(ILeb128::from_bytes(&[0xc0, 0]).try_decode_bits::<7>() == Err(Leb128Error::TooLong { max_bytes: 1, offset: 1 }));
+        assert!(ULeb128::from_bytes(&[0xff, 0xff, 0xff, 0xff, 0x1f]).decode_bits::<33>() == (1 << 33) - 1);
+
+        let mut buf = [0; 5];
+        assert!(ULeb128::encode_bits_to::<7>(127, &mut buf) == Ok(1));
+        assert!(ULeb128::encode_bits_to::<7>(128, &mut buf) == Err(EncodeError::OutOfRange { bits: 7 }));
+        assert!(ILeb128::encode_bits_to::<7>(-64, &mut buf) == Ok(1));
+        assert!(ILeb128::encode_bits_to::<7>(64, &mut buf) == Err(EncodeError::OutOfRange { bits: 7 }));
+        assert!(ILeb128::encode_bits_to::<128>(i128::MIN, &mut buf) == Err(EncodeError::BufferTooSmall { needed: 19 }));
+        assert!(ILeb128Owned::encode_bits::<33>(u32::MAX as i128).unwrap().decode_bits::<33>() == u32::MAX as i128);
+        assert!(ULeb128Owned::encode_bits::<128>(u128::MAX).unwrap().try_decode_bits::<128>() == Ok(u128::MAX));
+        assert!(ULeb128Owned::encode_bits::<1>(2) == Err(EncodeError::OutOfRange { bits: 1 }));
+    }
+
+    #[test]
+    fn test_reinterpret_bits() {
+        // i32.const -1, stored as a u32.
+        let mut buf = [0; 5];
+        assert!(ILeb128::encode_unsigned_bits_to::<32>(u32::MAX as u128, &mut buf) == Ok(1));
+        assert!(buf[0] == 0x7f);
+        assert!(ILeb128::encode_unsigned_bits_to::<32>(1 << 32, &mut buf) == Err(EncodeError::OutOfRange { bits: 32 }));
+        assert!(ILeb128::from_bytes(&[0x7f]).try_decode_unsigned_bits::<32>() == Ok(u32::MAX as u128));
+        assert!(ILeb128::from_bytes(&[0x80, 0x7f]).try_decode_unsigned_bits::<8>() == Ok(0x80));
+
+        assert!(ULeb128::encode_signed_bits_to::<8>(-1, &mut buf) == Ok(2));
+        assert!(buf[..2] == [0xff, 1]);
+        assert!(ULeb128::encode_signed_bits_to::<8>(128, &mut buf) == Err(EncodeError::OutOfRange { bits: 8 }));
+        assert!(ULeb128::from_bytes(&[0xff, 1]).try_decode_signed_bits::<8>() == Ok(-1));
+        assert!(ULeb128::from_bytes(&[0x7f]).try_decode_signed_bits::<8>() == Ok(127));
+
+        let owned = ILeb128Owned::encode_unsigned_bits::<128>(u128::MAX).unwrap();
+        assert!(owned == -1i8 && owned.try_decode_unsigned_bits::<128>() == Ok(u128::MAX));
+        let owned = ULeb128Owned::encode_signed_bits::<64>(i64::MIN as i128).unwrap();
+        assert!(owned == 1u64 << 63 && owned.try_decode_signed_bits::<64>() == Ok(i64::MIN as i128));
+    }
+
+    #[test]
+    fn test_signed_unsigned_conversion() {
+        let unsigned = ULeb128Owned::try_from(ILeb128Owned::from_bytes(&[0xc0, 0])).unwrap();
+        assert!(unsigned == 64u8 && unsigned.is_canonical());
+        assert!(ULeb128Owned::try_from(ILeb128Owned::from_bytes(&[0x80, 0x7f])) ==
+                Err(Leb128Error::Negative { offset: 1 }));
+        assert!(ULeb128Owned::try_from(i128::MAX.encode()).unwrap() == i128::MAX as u128);
+
+        let signed = ILeb128Owned::from(64u8.encode());
+        assert!(signed == 64i8 && signed.is_canonical());
+        assert!(ILeb128Owned::from(ULeb128Owned::from_bytes(&[0x80, 0x80, 0])) == 0i8);
+        assert!(ILeb128Owned::from(u128::MAX.encode()).decode_bytes()[..16] == [0xff; 16]);
+    }
+
+    #[test]
+    fn test_zigzag() {
+        for &value in &[0i64, -1, 1, -64, 64, i64::MIN, i64::MAX] {
+            let encoded = ULeb128Owned::encode_zigzag(value);
+            assert!(encoded == value.to_zigzag());
+            assert!(encoded.decode_zigzag::<i64>() == value);
+        }
+        assert!(ULeb128Owned::encode_zigzag(-1i8).as_leb128().0 == [0x01]);
+        assert!(ULeb128Owned::encode_zigzag(i128::MIN).decode_zigzag::<i128>() == i128::MIN);
+        assert!(ULeb128Owned::encode_zigzag(i128::MIN).0.is_inline());
+
+        assert!(ULeb128::from_bytes(&[0xfe, 0x03]).try_decode_zigzag::<i8>() ==
+                Err(Leb128Error::Overflow { target_bits: 8, offset: 1 }));
+        assert!(ULeb128::from_bytes(&[0xfe, 0x03]).decode_zigzag::<i16>() == 255);
+        let decoded: Result<Vec<i32>, _> = ULeb128::iter(&[0x00, 0x7f, 0x80, 0x01])
+            .decode(ULeb128::try_decode_zigzag::<i32>)
+            .collect();
+        assert!(decoded.unwrap() == [0, -64, 64]);
+    }
+
+    #[test]
+    fn test_inline_storage() {
+        assert!(u128::MAX.encode().0.is_inline());
+        assert!(i128::MIN.encode().0.is_inline());
+        assert!(ULeb128Owned::from_bytes(&[0xE5, 0x8E, 0x26]).clone().0.is_inline());
+        assert!(format!("{:?}", 624485u32.encode()) == "ULeb128Owned([229, 142, 38])");
+
+        let wide = ToULeb128Owned::encode(&[0xff; 20][..]);
+        assert!(!wide.0.is_inline());
+        assert!(wide.decode_bytes() == [0xff; 20]);
+        let mut bytes = [0x80; 24];
+        bytes[0] = 0x81;
+        bytes[23] = 0;
+        let padded = ULeb128Owned::from_bytes(&bytes);
+        assert!(!padded.0.is_inline() && padded.len() == 24 && padded == 1u8);
+        assert!(padded.canonicalize().0.is_inline());
+    }
+
     #[test]
     fn test_byte_count() {
         assert!(ILeb128Owned::from_bytes(&[2]).byte_count() == 1);
@@ -552,6 +1854,63 @@ mod test {
         assert!(ULeb128Owned::from_bytes(&[128, 128, 128, 128, 128, 128, 128, 128, 128, 2]).byte_count() == 10);
     }
 
-    // TODO test invalid from_bytes
-    // TODO test all_from_bytes (including invalid bytes)
+    #[test]
+    fn test_try_from_bytes() {
+        assert!(ULeb128::try_from_bytes(&[0xE5, 0x8E, 0x26, 0x42]).unwrap().byte_count() == 3);
+        assert!(ILeb128::try_from_bytes(&[0x7e]).unwrap().byte_count() == 1);
+        assert!(ULeb128::try_from_bytes(&[]) == Err(Leb128Error::Truncated { offset: 0 }));
+        assert!(ULeb128::try_from_bytes(&[0xE5, 0x8E]) == Err(Leb128Error::Truncated { offset: 2 }));
+        assert!(ILeb128Owned::try_from_bytes(&[0x80]) == Err(Leb128Error::Truncated { offset: 1 }));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_from_bytes_invalid() {
+        ULeb128::from_bytes(&[128, 128]);
+    }
+
+    #[test]
+    fn test_all_from_bytes() {
+        let all = ULeb128::all_from_bytes(&[0xE5, 0x8E, 0x26, 42, 128, 1]);
+        assert!(all.len() == 3);
+        assert!(all[0].expect_u32() == 624485);
+        assert!(all[1].expect_u32() == 42);
+        assert!(all[2].expect_u32() == 128);
+        assert!(ULeb128::all_from_bytes(&[]).is_empty());
+
+        assert!(ILeb128Owned::try_all_from_bytes(&[0x7e, 0xff, 0x7e]).unwrap().len() == 2);
+        assert!(ULeb128::try_all_from_bytes(&[42, 128, 128]) == Err(Leb128Error::Truncated { offset: 3 }));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_all_from_bytes_invalid() {
+        ILeb128::all_from_bytes(&[42, 128]);
+    }
+
+    #[test]
+    fn test_try_decode_errors() {
+        assert!(ULeb128Owned::from_bytes(&[255, 1]).try_decode_u8() == Ok(255));
+        assert!(ULeb128Owned::from_bytes(&[128, 2]).try_decode_u8() ==
+                Err(Leb128Error::Overflow { target_bits: 8, offset: 1 }));
+        assert!(ULeb128Owned::from_bytes(&[128, 128, 0]).try_decode_u8() ==
+                Err(Leb128Error::TooLong { max_bytes: 2, offset: 2 }));
+        assert!(ULeb128Owned::from_bytes(&[128, 128, 128, 128, 0]).try_decode_u32() == Ok(0));
+        assert!(ULeb128Owned::from_bytes(&[128, 128, 128, 128, 128, 0]).try_decode_u32() ==
+                Err(Leb128Error::TooLong { max_bytes: 5, offset: 5 }));
+
+        assert!(ILeb128Owned::from_bytes(&[0x80, 1]).try_decode_i8() ==
+                Err(Leb128Error::Overflow { target_bits: 8, offset: 1 }));
+        assert!(ILeb128Owned::from_bytes(&[0xff, 0x7e]).try_decode_i8() ==
+                Err(Leb128Error::Overflow { target_bits: 8, offset: 1 }));
+        assert!(ILeb128Owned::from_bytes(&[0xff, 0x7e]).try_decode_i16() == Ok(-129));
+        assert!(ILeb128Owned::from_bytes(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x7f]).try_decode_i64() ==
+                Ok(-(1 << 35)));
+        assert!(ILeb128Owned::from_bytes(&[0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]).try_decode_i32() ==
+                Err(Leb128Error::TooLong { max_bytes: 5, offset: 5 }));
+
+        let e = Leb128Error::Overflow { target_bits: 8, offset: 1 };
+        assert!(e.offset() == 1);
+        assert!(e.to_string() == "LEB128 number overflows 8-bit integer at offset 1");
+    }
 }
@@ -0,0 +1,78 @@
+//! Backing storage for the owned LEB128 types.
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+
+// The longest encoding which is stored inline, enough for any integer type.
+const INLINE_BYTES: usize = 19;
+
+// The bytes of an owned number. The encoding of any integer type is stored
+// inline, only longer numbers (e.g., from encoding a byte slice) are stored on
+// the heap.
+#[derive(Clone)]
+pub(crate) enum Storage {
+    Inline { bytes: [u8; INLINE_BYTES], len: u8 },
+    Heap(Vec<u8>),
+}
+
+impl Storage {
+    pub(crate) fn from_slice(bytes: &[u8]) -> Storage {
+        if bytes.len() > INLINE_BYTES {
+            return Storage::Heap(bytes.to_vec());
+        }
+        let mut inline = [0; INLINE_BYTES];
+        inline[..bytes.len()].copy_from_slice(bytes);
+        Storage::Inline { bytes: inline, len: bytes.len() as u8 }
+    }
+
+    pub(crate) fn from_vec(bytes: Vec<u8>) -> Storage {
+        if bytes.len() > INLINE_BYTES {
+            return Storage::Heap(bytes);
+        }
+        Storage::from_slice(&bytes)
+    }
+
+    // `len` zero bytes, to be encoded into.
+    pub(crate) fn zeroed(len: usize) -> Storage {
+        if len > INLINE_BYTES {
+            return Storage::Heap(vec![0; len]);
+        }
+        Storage::Inline { bytes: [0; INLINE_BYTES], len: len as u8 }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn is_inline(&self) -> bool {
+        match *self {
+            Storage::Inline { .. } => true,
+            Storage::Heap(_) => false,
+        }
+    }
+}
+
+impl Deref for Storage {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match *self {
+            Storage::Inline { ref bytes, len } => &bytes[..len as usize],
+            Storage::Heap(ref bytes) => bytes,
+        }
+    }
+}
+
+impl DerefMut for Storage {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match *self {
+            Storage::Inline { ref mut bytes, len } => &mut bytes[..len as usize],
+            Storage::Heap(ref mut bytes) => bytes,
+        }
+    }
+}
+
+// Debug as the bytes, regardless of where they are stored.
+impl fmt::Debug for Storage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
@@ -0,0 +1,130 @@
+//! ZigZag encoding of signed integers as unsigned LEB128, as used by Protocol
+//! Buffers' `sint` types, Avro and Thrift's compact protocol.
+
+use {read_uleb128, EncodeError, EncodeULeb128, FromLeb128, Leb128Error, ToLeb128};
+
+/// Signed integer types which can be encoded as ZigZag-mapped unsigned LEB128.
+///
+/// ZigZag maps 0, -1, 1, -2, 2, ... to 0, 1, 2, 3, 4, ..., so that numbers
+/// with a small magnitude have a short encoding whatever their sign. See
+/// `ULeb128::try_decode_zigzag` and `ULeb128Owned::encode_zigzag`.
+pub trait ZigZag: Copy {
+    /// The unsigned type of the same width.
+    type Unsigned: EncodeULeb128 + ToLeb128 + FromLeb128;
+
+    fn to_zigzag(self) -> Self::Unsigned;
+
+    fn from_zigzag(value: Self::Unsigned) -> Self;
+
+    /// Encode into the start of `buf` and return the number of bytes written.
+    /// Panics if `buf` is too short; the `MAX_BYTES` of `Unsigned` is always
+    /// long enough.
+    fn encode_zigzag_to(self, buf: &mut [u8]) -> usize {
+        self.to_zigzag().encode_uleb128_to(buf)
+    }
+
+    /// Encode into a stack-allocated array. Returns the array and the number
+    /// of bytes of it which are used.
+    fn encode_zigzag_array(self) -> (<Self::Unsigned as EncodeULeb128>::Array, usize) {
+        self.to_zigzag().encode_uleb128_array()
+    }
+
+    /// Encode into the start of `buf` and return the number of bytes written,
+    /// or an error if `buf` is too short. `buf` is not modified on error.
+    fn try_encode_zigzag_to(self, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        self.to_zigzag().try_encode_uleb128_to(buf)
+    }
+
+    /// The number of bytes in the encoding of this value.
+    fn zigzag_len(self) -> usize {
+        self.to_zigzag().leb128_len()
+    }
+}
+
+macro_rules! impl_zigzag {
+    ($t: ident, $u: ident) => {
+        impl ZigZag for $t {
+            type Unsigned = $u;
+
+            fn to_zigzag(self) -> $u {
+                ((self << 1) ^ (self >> ($t::BITS - 1))) as $u
+            }
+
+            fn from_zigzag(value: $u) -> $t {
+                (value >> 1) as $t ^ -((value & 1) as $t)
+            }
+        }
+    }
+}
+
+impl_zigzag!(i8, u8);
+impl_zigzag!(i16, u16);
+impl_zigzag!(i32, u32);
+impl_zigzag!(i64, u64);
+impl_zigzag!(i128, u128);
+impl_zigzag!(isize, usize);
+
+// Map a decoded ZigZag value back to two's complement. Truncating the result
+// gives the value for any narrower type.
+pub(crate) fn from_zigzag_bits(bits: u128) -> u128 {
+    (bits >> 1) ^ (bits & 1).wrapping_neg()
+}
+
+/// Read a ZigZag-mapped unsigned LEB128 number from the start of `input` and
+/// advance `input` past it, e.g., `read_zigzag::<i32>(&mut input)`. `input` is
+/// not changed on error.
+pub fn read_zigzag<T: ZigZag>(input: &mut &[u8]) -> Result<T, Leb128Error> {
+    read_uleb128::<T::Unsigned>(input).map(T::from_zigzag)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_zigzag_mapping() {
+        assert!(0i32.to_zigzag() == 0);
+        assert!((-1i32).to_zigzag() == 1);
+        assert!(1i32.to_zigzag() == 2);
+        assert!((-2i32).to_zigzag() == 3);
+        assert!(i32::MAX.to_zigzag() == u32::MAX - 1);
+        assert!(i32::MIN.to_zigzag() == u32::MAX);
+        assert!(i8::MIN.to_zigzag() == u8::MAX);
+        assert!(i128::MIN.to_zigzag() == u128::MAX);
+        for &value in &[0i64, -1, 1, -64, 64, i64::MIN, i64::MAX] {
+            assert!(i64::from_zigzag(value.to_zigzag()) == value);
+            assert!(from_zigzag_bits(value.to_zigzag() as u128) as i64 == value);
+        }
+        for value in i8::MIN..=i8::MAX {
+            assert!(i8::from_zigzag(value.to_zigzag()) == value);
+        }
+        assert!(i128::from_zigzag(u128::MAX) == i128::MIN);
+        assert!(from_zigzag_bits(u128::MAX) as i128 == i128::MIN);
+    }
+
+    #[test]
+    fn test_zigzag_encode() {
+        let mut buf = [0; 3];
+        assert!((-64i32).encode_zigzag_to(&mut buf) == 1);
+        assert!(buf[0] == 0x7f);
+        assert!(64i32.encode_zigzag_to(&mut buf) == 2);
+        assert!(buf[..2] == [0x80, 0x01]);
+        assert!(i16::MIN.zigzag_len() == 3);
+        let (array, count) = i16::MIN.encode_zigzag_array();
+        assert!(array[..count] == [0xff, 0xff, 0x03]);
+        assert!(i32::MIN.try_encode_zigzag_to(&mut buf) == Err(EncodeError::BufferTooSmall { needed: 5 }));
+        assert!(i128::MAX.zigzag_len() == 19);
+    }
+
+    #[test]
+    fn test_read_zigzag() {
+        let bytes = [0x03, 0x80, 0x01, 0xff, 0x03];
+        let mut input = &bytes[..];
+        assert!(read_zigzag::<i32>(&mut input) == Ok(-2));
+        assert!(read_zigzag::<i8>(&mut input) == Ok(64));
+        assert!(read_zigzag::<i8>(&mut input) == Err(Leb128Error::Overflow { target_bits: 8, offset: 1 }));
+        assert!(input == [0xff, 0x03]);
+        assert!(read_zigzag::<i16>(&mut input) == Ok(-256));
+        assert!(input.is_empty());
+    }
+}